@@ -2,24 +2,24 @@ use crate::{
     solutions::{
         day01, day02, day03, day04, day05, day06, day07, day08, day09, day10, day11, day12, day13,
     },
-    utils::date::Date,
+    utils::{date::Date, options::Options},
 };
 
 pub mod solutions;
 pub mod utils;
 
 /// Wrapper for solving the problem for a given date.
-pub fn solve(date: Date) {
+pub fn solve(date: Date, options: &Options) {
     match date {
         Date::Day01 => day01::solve(),
-        Date::Day02 => day02::solve(),
-        Date::Day03 => day03::solve(),
-        Date::Day04 => day04::solve(),
-        Date::Day05 => day05::solve(),
-        Date::Day06 => day06::solve(),
-        Date::Day07 => day07::solve(),
-        Date::Day08 => day08::solve(),
-        Date::Day09 => day09::solve(),
+        Date::Day02 => day02::solve(options),
+        Date::Day03 => day03::solve(options),
+        Date::Day04 => day04::solve(options),
+        Date::Day05 => day05::solve(options),
+        Date::Day06 => day06::solve(options),
+        Date::Day07 => day07::solve(options),
+        Date::Day08 => day08::solve(options),
+        Date::Day09 => day09::solve(options),
         Date::Day10 => day10::solve(),
         Date::Day11 => day11::solve(),
         Date::Day12 => day12::solve(),
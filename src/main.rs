@@ -1,4 +1,7 @@
-use aoc_2022::{solve, utils::date::Date};
+use aoc_2022::{
+    solve,
+    utils::{date::Date, options::Options},
+};
 use clap::Parser;
 
 /// Advent of Code 2022
@@ -7,9 +10,12 @@ struct Args {
     /// The date of the month
     #[arg(value_enum)]
     date: Date,
+
+    #[command(flatten)]
+    options: Options,
 }
 
 fn main() {
     let args = Args::parse();
-    solve(args.date);
+    solve(args.date, &args.options);
 }
@@ -1,68 +1,214 @@
-use crate::utils::{date::Date, io::read_input};
+use std::{error::Error, fmt::Display, str::FromStr};
 
-enum Part {
-    Part1,
-    Part2,
+use anyhow::{anyhow, bail, ensure, Result};
+use itertools::Itertools;
+
+use crate::utils::{date::Date, io::read_input, options::Options};
+
+/// A game where the choices form a cycle and every choice beats
+/// the half of the other choices that come right before it.
+///
+/// With three choices this is rock-paper-scissors. Any odd number of
+/// choices works, since every choice then beats as many as it loses to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Game {
+    choices: Vec<String>,
 }
 
-#[derive(Debug, PartialEq)]
-enum Choice {
-    Rock,
-    Paper,
-    Scissors,
+/// A choice in a [`Game`], identified by its position in the cycle.
+/// The position also gives the score for playing it: the first choice scores 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Choice(usize);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Outcome {
+    Lose,
+    Draw,
+    Win,
 }
 
-impl Choice {
+impl Outcome {
+    /// `X`, `Y` and `Z` mean lose, draw and win.
+    fn from_letter(letter: char) -> Option<Self> {
+        match letter {
+            'X' => Some(Outcome::Lose),
+            'Y' => Some(Outcome::Draw),
+            'Z' => Some(Outcome::Win),
+            _ => None,
+        }
+    }
+
     fn score(&self) -> i32 {
         match self {
-            Choice::Rock => 1,
-            Choice::Paper => 2,
-            Choice::Scissors => 3,
+            Outcome::Lose => 0,
+            Outcome::Draw => 3,
+            Outcome::Win => 6,
         }
     }
+}
+
+impl Game {
+    /// Opponent choices use letters from `A`, player choices use letters up to `Z`,
+    /// so the two columns stay apart for at most 13 choices.
+    const MAX_CHOICES: usize = 13;
 
-    fn opponent_choice(choice: char) -> Self {
-        match choice {
-            'A' => Choice::Rock,
-            'B' => Choice::Paper,
-            'C' => Choice::Scissors,
-            _ => panic!("invalid opponent choice"),
+    /// Creates a game from its choices in cycle order.
+    pub fn new(choices: Vec<String>) -> Result<Self> {
+        if choices.len() < 3 || choices.len().is_multiple_of(2) || choices.len() > Self::MAX_CHOICES
+        {
+            bail!(
+                "A game needs an odd number of choices between 3 and {}, got {}",
+                Self::MAX_CHOICES,
+                choices.len()
+            );
         }
+
+        Ok(Self { choices })
+    }
+
+    /// Rock-paper-scissors.
+    pub fn rps() -> Self {
+        Self::named(&["Rock", "Paper", "Scissors"])
+    }
+
+    /// Rock-paper-scissors-lizard-Spock.
+    pub fn rpsls() -> Self {
+        Self::named(&["Rock", "Spock", "Paper", "Lizard", "Scissors"])
     }
 
-    /// In part 1, a `char` simply corresponds to a choice.
-    fn part1_player_choice(choice: char) -> Self {
-        match choice {
-            'X' => Choice::Rock,
-            'Y' => Choice::Paper,
-            'Z' => Choice::Scissors,
-            _ => panic!("invalid player choice"),
+    fn named(choices: &[&str]) -> Self {
+        Self {
+            choices: choices.iter().map(|choice| choice.to_string()).collect(),
         }
     }
 
-    /// In part 2, the `char` choice is a bit more complex.
-    /// Based on the opponent's choice, the `char` choice describes whether
-    /// the player should lose, draw or win against the opponent.
-    fn part2_player_choice(opponent: &Choice, player_choice: char) -> Self {
-        match opponent {
-            Choice::Rock => match player_choice {
-                'X' => Choice::Scissors,
-                'Y' => Choice::Rock,
-                'Z' => Choice::Paper,
-                _ => panic!("invalid player choice"),
-            },
-            Choice::Paper => match player_choice {
-                'X' => Choice::Rock,
-                'Y' => Choice::Paper,
-                'Z' => Choice::Scissors,
-                _ => panic!("invalid player choice"),
-            },
-            Choice::Scissors => match player_choice {
-                'X' => Choice::Paper,
-                'Y' => Choice::Scissors,
-                'Z' => Choice::Rock,
-                _ => panic!("invalid player choice"),
-            },
+    fn size(&self) -> usize {
+        self.choices.len()
+    }
+
+    fn choices(&self) -> impl Iterator<Item = Choice> {
+        (0..self.size()).map(Choice)
+    }
+
+    fn name(&self, choice: Choice) -> &str {
+        &self.choices[choice.0]
+    }
+
+    fn score(&self, choice: Choice) -> i32 {
+        choice.0 as i32 + 1
+    }
+
+    /// Gets the outcome of a round for the player.
+    fn outcome(&self, opponent: Choice, player: Choice) -> Outcome {
+        // How many steps the player's choice is ahead of the opponent's in the cycle.
+        let distance = (player.0 + self.size() - opponent.0) % self.size();
+
+        if distance == 0 {
+            Outcome::Draw
+        } else if distance <= self.size() / 2 {
+            Outcome::Win
+        } else {
+            Outcome::Lose
+        }
+    }
+
+    /// Gets the choice that leads to `outcome` against the opponent.
+    /// When several choices do, the one closest to the opponent's in the cycle is taken.
+    fn choice_for(&self, opponent: Choice, outcome: Outcome) -> Choice {
+        let offset = match outcome {
+            Outcome::Lose => self.size() - 1,
+            Outcome::Draw => 0,
+            Outcome::Win => 1,
+        };
+
+        Choice((opponent.0 + offset) % self.size())
+    }
+
+    /// Opponent choices are `A`, `B`, `C` etc.
+    fn opponent_choice(&self, letter: char) -> Option<Choice> {
+        let index = (letter as usize).wrapping_sub('A' as usize);
+        (index < self.size()).then_some(Choice(index))
+    }
+
+    /// Player choices are the letters up to `Z`, so `X`, `Y` and `Z`
+    /// for three choices and `V` to `Z` for five.
+    fn player_letter(&self, letter: char) -> Option<usize> {
+        let first = b'Z' as usize + 1 - self.size();
+        let index = (letter as usize).wrapping_sub(first);
+        (index < self.size()).then_some(index)
+    }
+
+    fn player_letters(&self) -> impl Iterator<Item = char> {
+        let first = b'Z' + 1 - self.size() as u8;
+        (first..=b'Z').map(char::from)
+    }
+}
+
+impl FromStr for Game {
+    type Err = anyhow::Error;
+
+    /// Parses `rps`, `rpsls`, a number of choices like `7`, or the names
+    /// of the choices in cycle order like `rock,paper,scissors`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rps" => Ok(Self::rps()),
+            "rpsls" => Ok(Self::rpsls()),
+            _ if s.contains(',') => Self::new(s.split(',').map(str::to_string).collect()),
+            _ => {
+                let size = s
+                    .parse::<usize>()
+                    .map_err(|_| anyhow!("Invalid game `{}`", s))?;
+                Self::new((1..=size).map(|choice| choice.to_string()).collect())
+            }
+        }
+    }
+}
+
+/// How the second column of the strategy guide is read.
+#[derive(Debug, Clone, PartialEq)]
+enum Interpretation {
+    /// The player letters are the choices to play, in that order.
+    Choices(Vec<Choice>),
+    /// `X`, `Y` and `Z` mean lose, draw and win.
+    Outcomes,
+}
+
+impl Interpretation {
+    /// The interpretation in part 1, where each player letter is the choice at its position.
+    fn in_order(game: &Game) -> Self {
+        Interpretation::Choices(game.choices().collect())
+    }
+
+    /// The largest game whose interpretations are all analysed.
+    /// The number of mappings grows factorially, to 5040 for seven choices.
+    const MAX_ANALYSED_CHOICES: usize = 7;
+
+    /// Every way the strategy guide could be read: all mappings from player
+    /// letters to choices, and the mapping to outcomes.
+    fn all(game: &Game) -> Result<Vec<Self>> {
+        ensure!(
+            game.size() <= Self::MAX_ANALYSED_CHOICES,
+            "Cannot analyse a game of {} choices, the limit is {}",
+            game.size(),
+            Self::MAX_ANALYSED_CHOICES
+        );
+
+        Ok(game
+            .choices()
+            .permutations(game.size())
+            .map(Interpretation::Choices)
+            .chain([Interpretation::Outcomes])
+            .collect())
+    }
+
+    fn describe(&self, game: &Game) -> String {
+        match self {
+            Interpretation::Choices(mapping) => game
+                .player_letters()
+                .zip(mapping)
+                .map(|(letter, &choice)| format!("{}={}", letter, game.name(choice)))
+                .join(" "),
+            Interpretation::Outcomes => "X=Lose Y=Draw Z=Win".to_string(),
         }
     }
 }
@@ -72,79 +218,181 @@ struct Round {
     player: Choice,
 }
 
-impl Round {
-    fn outcome(opponent: &Choice, player: &Choice) -> i32 {
-        match opponent {
-            Choice::Rock => match player {
-                Choice::Rock => 3,
-                Choice::Paper => 6,
-                Choice::Scissors => 0,
-            },
-            Choice::Paper => match player {
-                Choice::Rock => 0,
-                Choice::Paper => 3,
-                Choice::Scissors => 6,
-            },
-            Choice::Scissors => match player {
-                Choice::Rock => 6,
-                Choice::Paper => 0,
-                Choice::Scissors => 3,
-            },
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ParseMode {
+    /// Skip blank lines and ignore extra whitespace around the two columns.
+    Lenient,
+    /// Expect every line to be exactly two letters separated by a single space.
+    Strict,
+}
+
+/// A line of the strategy guide that could not be read, with its 1-based line number.
+#[derive(Debug, PartialEq)]
+enum ParseError {
+    /// The line has fewer than two columns.
+    MissingColumn { line: usize },
+    /// The line has more than two columns.
+    ExtraColumn { line: usize },
+    /// The first column is not an opponent letter in the game.
+    InvalidOpponent { line: usize, column: String },
+    /// The second column is not a player letter under the interpretation.
+    InvalidPlayer { line: usize, column: String },
+    /// A blank line, or whitespace other than a single separating space, in strict mode.
+    Whitespace { line: usize },
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::MissingColumn { line } => write!(f, "Line {}: missing column", line),
+            ParseError::ExtraColumn { line } => write!(f, "Line {}: too many columns", line),
+            ParseError::InvalidOpponent { line, column } => {
+                write!(f, "Line {}: invalid opponent choice `{}`", line, column)
+            }
+            ParseError::InvalidPlayer { line, column } => {
+                write!(f, "Line {}: invalid player choice `{}`", line, column)
+            }
+            ParseError::Whitespace { line } => write!(f, "Line {}: unexpected whitespace", line),
         }
     }
+}
+
+impl Error for ParseError {}
+
+/// Gets the only `char` of a column, if it has exactly one.
+fn letter(column: &str) -> Option<char> {
+    let mut chars = column.chars();
+    let letter = chars.next()?;
+    chars.next().is_none().then_some(letter)
+}
 
-    fn from_input(input: &[String], part: Part) -> Vec<Self> {
-        input
-            .iter()
-            .map(|line| line.split(' ').collect::<Vec<&str>>())
-            .map(|choices| {
-                let opponent_choice = choices[0].parse::<char>().expect("invalid opponent choice");
-                let player_choice = choices[1].parse::<char>().expect("invalid player choice");
+/// Reads the rounds of a strategy guide, with the second column read according to `interpretation`.
+fn parse_rounds(
+    input: &[String],
+    game: &Game,
+    interpretation: &Interpretation,
+    mode: ParseMode,
+) -> Result<Vec<Round>, ParseError> {
+    let mut rounds = Vec::new();
 
-                let opponent = Choice::opponent_choice(opponent_choice);
+    for (index, line) in input.iter().enumerate() {
+        let line_number = index + 1;
 
-                let player = match part {
-                    Part::Part1 => Choice::part1_player_choice(player_choice),
-                    Part::Part2 => Choice::part2_player_choice(&opponent, player_choice),
-                };
+        let columns = match mode {
+            ParseMode::Lenient if line.trim().is_empty() => continue,
+            ParseMode::Lenient => line.split_whitespace().collect::<Vec<_>>(),
+            ParseMode::Strict if line.is_empty() || line.trim() != line => {
+                return Err(ParseError::Whitespace { line: line_number })
+            }
+            ParseMode::Strict => line.split(' ').collect::<Vec<_>>(),
+        };
 
-                Round { opponent, player }
+        let (opponent, player) = match columns[..] {
+            [opponent, player] => (opponent, player),
+            [] | [_] => return Err(ParseError::MissingColumn { line: line_number }),
+            _ if mode == ParseMode::Strict && columns.contains(&"") => {
+                return Err(ParseError::Whitespace { line: line_number })
+            }
+            _ => return Err(ParseError::ExtraColumn { line: line_number }),
+        };
+
+        let opponent = letter(opponent)
+            .and_then(|letter| game.opponent_choice(letter))
+            .ok_or_else(|| ParseError::InvalidOpponent {
+                line: line_number,
+                column: opponent.to_string(),
+            })?;
+
+        let player = letter(player)
+            .and_then(|letter| match interpretation {
+                Interpretation::Choices(mapping) => {
+                    game.player_letter(letter).map(|index| mapping[index])
+                }
+                Interpretation::Outcomes => {
+                    Outcome::from_letter(letter).map(|outcome| game.choice_for(opponent, outcome))
+                }
             })
-            .collect::<Vec<Self>>()
+            .ok_or_else(|| ParseError::InvalidPlayer {
+                line: line_number,
+                column: player.to_string(),
+            })?;
+
+        rounds.push(Round { opponent, player });
     }
+
+    Ok(rounds)
 }
 
-fn total_score(rounds: &[Round]) -> i32 {
+fn total_score(game: &Game, rounds: &[Round]) -> i32 {
     rounds
         .iter()
         .map(|round| {
-            let score = round.player.score();
-            let outcome = Round::outcome(&round.opponent, &round.player);
+            let score = game.score(round.player);
+            let outcome = game.outcome(round.opponent, round.player);
 
-            score + outcome
+            score + outcome.score()
         })
         .sum()
 }
 
-fn part1(input: &[String]) -> i32 {
-    let rounds = Round::from_input(input, Part::Part1);
-    total_score(&rounds)
+fn part1(input: &[String], game: &Game, mode: ParseMode) -> Result<i32, ParseError> {
+    let rounds = parse_rounds(input, game, &Interpretation::in_order(game), mode)?;
+    Ok(total_score(game, &rounds))
 }
 
-fn part2(input: &[String]) -> i32 {
-    let rounds = Round::from_input(input, Part::Part2);
-    total_score(&rounds)
+fn part2(input: &[String], game: &Game, mode: ParseMode) -> Result<i32, ParseError> {
+    let rounds = parse_rounds(input, game, &Interpretation::Outcomes, mode)?;
+    Ok(total_score(game, &rounds))
 }
 
-pub fn solve() {
-    let input: Vec<String> = read_input(Date::Day02)
-        .expect("failed to read input")
+/// Scores the strategy guide under every interpretation, best first.
+fn analyse(input: &[String], game: &Game, mode: ParseMode) -> Result<Vec<(Interpretation, i32)>> {
+    let mut scores = Interpretation::all(game)?
         .into_iter()
-        .filter(|line: &String| !line.is_empty())
-        .collect();
+        .map(|interpretation| {
+            let rounds = parse_rounds(input, game, &interpretation, mode)?;
+            let score = total_score(game, &rounds);
+            Ok((interpretation, score))
+        })
+        .collect::<Result<Vec<_>, ParseError>>()?;
+
+    scores.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+    Ok(scores)
+}
+
+pub fn solve(options: &Options) {
+    let input: Vec<String> = read_input(Date::Day02).expect("failed to read input");
+
+    let game = options.game.clone().unwrap_or_else(Game::rps);
+    let mode = if options.strict {
+        ParseMode::Strict
+    } else {
+        ParseMode::Lenient
+    };
+
+    if options.analyse {
+        let scores = analyse(&input, &game, mode).expect("Failed to analyse the strategy guide");
 
-    println!("Day 1 Part 1: {:?}", part1(&input));
-    println!("Day 1 Part 2: {:?}", part2(&input));
+        for (interpretation, score) in &scores {
+            println!("Day 2 {}: {}", interpretation.describe(&game), score);
+        }
+
+        if let (Some((best, best_score)), Some((worst, worst_score))) =
+            (scores.first(), scores.last())
+        {
+            println!("Day 2 best: {} ({})", best.describe(&game), best_score);
+            println!("Day 2 worst: {} ({})", worst.describe(&game), worst_score);
+        }
+    }
+
+    println!(
+        "Day 1 Part 1: {:?}",
+        part1(&input, &game, mode).expect("Invalid strategy guide")
+    );
+    println!(
+        "Day 1 Part 2: {:?}",
+        part2(&input, &game, mode).expect("Invalid strategy guide")
+    );
 }
 
 #[cfg(test)]
@@ -166,12 +414,178 @@ C Z";
     #[test]
     fn day02_part1() {
         let actual = get_input();
-        assert_eq!(part1(&actual), 15);
+        assert_eq!(part1(&actual, &Game::rps(), ParseMode::Strict), Ok(15));
     }
 
     #[test]
     fn day02_part2() {
         let actual = get_input();
-        assert_eq!(part2(&actual), 12);
+        assert_eq!(part2(&actual, &Game::rps(), ParseMode::Strict), Ok(12));
+    }
+
+    #[test]
+    fn day02_analyse() {
+        let actual = get_input();
+        let game = Game::rps();
+        let scores = analyse(&actual, &game, ParseMode::Strict).unwrap();
+
+        assert_eq!(scores.len(), 7);
+        assert!(scores.contains(&(Interpretation::in_order(&game), 15)));
+        assert!(scores.contains(&(Interpretation::Outcomes, 12)));
+        assert_eq!(
+            scores.first(),
+            Some(&(
+                Interpretation::Choices(vec![Choice(2), Choice(1), Choice(0)]),
+                24
+            ))
+        );
+        assert_eq!(
+            scores.last(),
+            Some(&(
+                Interpretation::Choices(vec![Choice(0), Choice(2), Choice(1)]),
+                6
+            ))
+        );
+        assert_eq!(scores[0].0.describe(&game), "X=Scissors Y=Paper Z=Rock");
+
+        let seven = "7".parse().unwrap();
+        assert_eq!(
+            analyse(&actual, &seven, ParseMode::Strict).unwrap().len(),
+            5041
+        );
+
+        let nine = "9".parse().unwrap();
+        assert!(analyse(&actual, &nine, ParseMode::Strict).is_err());
+    }
+
+    #[test]
+    fn day02_rpsls() {
+        let game = Game::rpsls();
+        let choice = |name: &str| Choice(game.choices.iter().position(|c| c == name).unwrap());
+
+        // Every choice beats exactly two others.
+        for player in game.choices() {
+            let wins = game
+                .choices()
+                .filter(|&opponent| game.outcome(opponent, player) == Outcome::Win)
+                .count();
+            assert_eq!(wins, 2);
+        }
+
+        for (winner, loser) in [
+            ("Scissors", "Paper"),
+            ("Paper", "Rock"),
+            ("Rock", "Lizard"),
+            ("Lizard", "Spock"),
+            ("Spock", "Scissors"),
+            ("Scissors", "Lizard"),
+            ("Lizard", "Paper"),
+            ("Paper", "Spock"),
+            ("Spock", "Rock"),
+            ("Rock", "Scissors"),
+        ] {
+            assert_eq!(game.outcome(choice(loser), choice(winner)), Outcome::Win);
+            assert_eq!(game.outcome(choice(winner), choice(loser)), Outcome::Lose);
+        }
+
+        // With five choices the player letters are `V` to `Z`, so `X` is paper,
+        // `Y` is lizard and `Z` is scissors.
+        let input = ["A X", "E Z", "B Y"].map(String::from);
+        assert_eq!(
+            part1(&input, &game, ParseMode::Strict).unwrap(),
+            (3 + 6) + (5 + 3) + (4 + 6)
+        );
+        assert_eq!(
+            part2(&input, &game, ParseMode::Strict).unwrap(),
+            5 + (1 + 6) + (2 + 3)
+        );
+    }
+
+    #[test]
+    fn day02_game_from_str() {
+        assert_eq!("rps".parse::<Game>().unwrap(), Game::rps());
+        assert_eq!("7".parse::<Game>().unwrap().size(), 7);
+        assert_eq!(
+            "rock,paper,scissors".parse::<Game>().unwrap().choices,
+            ["rock", "paper", "scissors"]
+        );
+        assert!("4".parse::<Game>().is_err());
+        assert!("15".parse::<Game>().is_err());
+        assert!("rock,paper".parse::<Game>().is_err());
+    }
+
+    #[test]
+    fn day02_parse_lenient() {
+        let game = Game::rps();
+        let input = ["A Y  ", "", "B\tX", "   ", "C Z"].map(String::from);
+        let rounds = parse_rounds(&input, &game, &Interpretation::Outcomes, ParseMode::Lenient);
+
+        assert_eq!(rounds.map(|rounds| total_score(&game, &rounds)), Ok(12));
+    }
+
+    #[test]
+    fn day02_parse_errors() {
+        let game = Game::rps();
+        let parse = |lines: &[&str], mode| {
+            let input = lines
+                .iter()
+                .map(|line| line.to_string())
+                .collect::<Vec<_>>();
+            parse_rounds(&input, &game, &Interpretation::Outcomes, mode).err()
+        };
+
+        assert_eq!(
+            parse(&["A Y", "B"], ParseMode::Lenient),
+            Some(ParseError::MissingColumn { line: 2 })
+        );
+        assert_eq!(
+            parse(&["A Y Z"], ParseMode::Lenient),
+            Some(ParseError::ExtraColumn { line: 1 })
+        );
+        assert_eq!(
+            parse(&["A Y", "B X", "D Z"], ParseMode::Lenient),
+            Some(ParseError::InvalidOpponent {
+                line: 3,
+                column: "D".to_string()
+            })
+        );
+        assert_eq!(
+            parse(&["A XY"], ParseMode::Lenient),
+            Some(ParseError::InvalidPlayer {
+                line: 1,
+                column: "XY".to_string()
+            })
+        );
+        assert_eq!(parse(&["A Y", ""], ParseMode::Lenient), None);
+        assert_eq!(
+            parse(&["A Y", ""], ParseMode::Strict),
+            Some(ParseError::Whitespace { line: 2 })
+        );
+        assert_eq!(
+            parse(&["A Y "], ParseMode::Strict),
+            Some(ParseError::Whitespace { line: 1 })
+        );
+        assert_eq!(
+            parse(&["A  Y"], ParseMode::Strict),
+            Some(ParseError::Whitespace { line: 1 })
+        );
+
+        // Under the part 1 reading of a five-choice game, `V` is a valid player letter.
+        let rpsls = Game::rpsls();
+        let input = ["A V".to_string()];
+        assert!(parse_rounds(
+            &input,
+            &rpsls,
+            &Interpretation::in_order(&rpsls),
+            ParseMode::Strict
+        )
+        .is_ok());
+        assert_eq!(
+            parse_rounds(&input, &rpsls, &Interpretation::Outcomes, ParseMode::Strict).err(),
+            Some(ParseError::InvalidPlayer {
+                line: 1,
+                column: "V".to_string()
+            })
+        );
     }
 }
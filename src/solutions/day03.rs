@@ -1,78 +1,371 @@
+use std::{fmt::Display, str::FromStr};
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
 use itertools::Itertools;
 
-use crate::utils::{date::Date, io::read_input};
-
-/// Closure that calculates the priority of a character.
-/// Characters a - z have a priority of 1 - 26.
-/// Characters A - Z have a priority of 27 - 52.
-/// Characters that are not letters have a priority of 0.
-fn calculate_priority() -> impl Fn(Option<char>) -> i32 {
-    |item_type| {
-        if let Some(character) = item_type {
-            match character {
-                'a'..='z' => (character as i32) - ('a' as i32) + 1,
-                'A'..='Z' => (character as i32) - ('A' as i32) + 27,
-                _ => 0,
-            }
-        } else {
-            0
+use crate::utils::{date::Date, io::read_input, options::Options};
+
+/// Gets the priority of an item.
+/// Items a - z have a priority of 1 - 26.
+/// Items A - Z have a priority of 27 - 52.
+/// Anything else is not an item.
+fn priority(item: char) -> Option<u32> {
+    match item {
+        'a'..='z' => Some(item as u32 - 'a' as u32 + 1),
+        'A'..='Z' => Some(item as u32 - 'A' as u32 + 27),
+        _ => None,
+    }
+}
+
+/// Gets the item with a priority, the inverse of [`priority`].
+fn item(priority: u32) -> char {
+    match priority {
+        1..=26 => char::from(b'a' + (priority - 1) as u8),
+        27..=52 => char::from(b'A' + (priority - 27) as u8),
+        _ => panic!("Invalid priority {}", priority),
+    }
+}
+
+/// A set of item types, stored as one bit per priority.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+struct ItemSet(u64);
+
+impl ItemSet {
+    /// The set with every item type.
+    const ALL: ItemSet = ItemSet((1 << 52) - 1);
+
+    fn insert(&mut self, item: char) -> Result<()> {
+        let priority = priority(item).ok_or_else(|| anyhow!("Invalid item `{}`", item))?;
+        self.0 |= 1 << (priority - 1);
+        Ok(())
+    }
+
+    fn intersection(self, other: ItemSet) -> ItemSet {
+        ItemSet(self.0 & other.0)
+    }
+
+    fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    /// Iterates over the priorities in the set, lowest first.
+    fn priorities(&self) -> impl Iterator<Item = u32> {
+        let bits = self.0;
+        (1..=52).filter(move |priority| bits & (1 << (priority - 1)) != 0)
+    }
+
+    fn items(&self) -> impl Iterator<Item = char> {
+        self.priorities().map(item)
+    }
+
+    /// Gets the priority of the only item in the set.
+    fn single_priority(&self) -> Result<u32> {
+        match self.len() {
+            0 => bail!("No common item"),
+            1 => Ok(self.0.trailing_zeros() + 1),
+            _ => bail!("More than one common item: `{}`", self),
         }
     }
 }
 
-fn part1(input: &[String]) -> i32 {
-    let priority_sum = input
-        .iter()
-        .map(|line| {
-            // Get the common character in the first half and the second half of the line.
-            let middle = line.len() / 2;
-            let first_half = &line[..middle];
-            let second_half = &line[middle..];
-
-            let mut common: Option<char> = None;
-            for second_char in second_half.chars() {
-                if first_half.contains(second_char) {
-                    common = Some(second_char);
-                    break;
-                }
-            }
+impl FromStr for ItemSet {
+    type Err = anyhow::Error;
 
-            common
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut items = ItemSet::default();
+        for item in s.chars() {
+            items.insert(item)?;
+        }
+        Ok(items)
+    }
+}
+
+impl Display for ItemSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.items().collect::<String>())
+    }
+}
+
+/// Gets the items found in every one of the given rucksacks.
+fn common_items<'a>(rucksacks: impl IntoIterator<Item = &'a str>) -> Result<ItemSet> {
+    rucksacks
+        .into_iter()
+        .try_fold(ItemSet::ALL, |common, rucksack| {
+            Ok(common.intersection(rucksack.parse()?))
         })
-        .map(calculate_priority())
-        .sum::<i32>();
+}
 
-    priority_sum
+/// Gets the items found in both compartments of each rucksack.
+fn compartment_items(input: &[String]) -> Result<Vec<ItemSet>> {
+    input
+        .iter()
+        .enumerate()
+        .map(|(index, line)| {
+            let length = line.chars().count();
+            ensure!(
+                length.is_multiple_of(2),
+                "Line {}: rucksack has an odd number of items",
+                index + 1
+            );
+
+            // Split on a character boundary, so that invalid multibyte items
+            // are reported by the item set instead of breaking the split.
+            let middle = line
+                .char_indices()
+                .nth(length / 2)
+                .map_or(line.len(), |(middle, _)| middle);
+            let (first_half, second_half) = line.split_at(middle);
+            common_items([first_half, second_half]).with_context(|| format!("Line {}", index + 1))
+        })
+        .collect()
 }
 
-fn part2(input: &[String]) -> i32 {
-    let priority_sum = input
+/// Gets the items carried by every elf of each group of `group_size` elves.
+fn group_items(input: &[String], group_size: usize) -> Result<Vec<ItemSet>> {
+    ensure!(group_size > 0, "Groups need at least one elf");
+    ensure!(
+        input.len().is_multiple_of(group_size),
+        "{} rucksacks cannot be split into groups of {}",
+        input.len(),
+        group_size
+    );
+
+    input
         .iter()
-        .tuples::<(_, _, _)>()
-        .map(|(first, second, third)| {
-            // Iterate over all possible combinations of the three lines
-            let mut common: Option<char> = None;
-
-            for first_char in first.chars() {
-                if second.contains(first_char) && third.contains(first_char) {
-                    common = Some(first_char);
-                    break;
-                }
-            }
+        .chunks(group_size)
+        .into_iter()
+        .enumerate()
+        .map(|(index, group)| {
+            common_items(group.map(String::as_str)).with_context(|| format!("Group {}", index + 1))
+        })
+        .collect()
+}
 
-            common
+/// Sums the priorities of the sets, each of which must hold exactly one item.
+fn priority_sum(sets: &[ItemSet], label: &str) -> Result<u32> {
+    sets.iter()
+        .enumerate()
+        .map(|(index, set)| {
+            set.single_priority()
+                .with_context(|| format!("{} {}", label, index + 1))
         })
-        .map(calculate_priority())
-        .sum::<i32>();
+        .sum()
+}
+
+/// Something wrong with the rucksacks, with 1-based line and group numbers.
+#[derive(Debug, PartialEq)]
+enum Anomaly {
+    /// The rucksack cannot be split into two equal compartments.
+    OddLength { line: usize, length: usize },
+    /// The rucksack holds something that is not an item type.
+    InvalidItem { line: usize, item: char },
+    /// No item is in both compartments of the rucksack.
+    NoSharedItem { line: usize },
+    /// More than one item is in both compartments of the rucksack.
+    MultipleSharedItems { line: usize, items: ItemSet },
+    /// No item is carried by every elf in the group.
+    NoBadge { group: usize },
+    /// More than one item is carried by every elf in the group.
+    MultipleBadges { group: usize, badges: ItemSet },
+    /// The last group has fewer elves than the group size.
+    IncompleteGroup { group: usize, size: usize },
+    /// The rucksacks cannot be split into groups of no elves.
+    ZeroGroupSize,
+}
+
+impl Display for Anomaly {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Anomaly::OddLength { line, length } => {
+                write!(f, "Line {}: odd number of items ({})", line, length)
+            }
+            Anomaly::InvalidItem { line, item } => {
+                write!(f, "Line {}: invalid item `{}`", line, item)
+            }
+            Anomaly::NoSharedItem { line } => {
+                write!(f, "Line {}: compartments share no item", line)
+            }
+            Anomaly::MultipleSharedItems { line, items } => {
+                write!(
+                    f,
+                    "Line {}: compartments share more than one item `{}`",
+                    line, items
+                )
+            }
+            Anomaly::NoBadge { group } => write!(f, "Group {}: no badge", group),
+            Anomaly::MultipleBadges { group, badges } => {
+                write!(f, "Group {}: more than one badge `{}`", group, badges)
+            }
+            Anomaly::IncompleteGroup { group, size } => {
+                write!(f, "Group {}: incomplete ({} rucksacks)", group, size)
+            }
+            Anomaly::ZeroGroupSize => write!(f, "Groups need at least one elf"),
+        }
+    }
+}
+
+/// The result of checking every rucksack and group.
+#[derive(Debug)]
+struct Diagnostics {
+    rucksacks: usize,
+    groups: usize,
+    anomalies: Vec<Anomaly>,
+}
 
-    priority_sum
+impl Diagnostics {
+    fn is_valid(&self) -> bool {
+        self.anomalies.is_empty()
+    }
 }
 
-pub fn solve() {
+impl Display for Diagnostics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Checked {} rucksacks in {} groups: {} anomalies",
+            self.rucksacks,
+            self.groups,
+            self.anomalies.len()
+        )?;
+
+        for anomaly in &self.anomalies {
+            write!(f, "\n{}", anomaly)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Checks every rucksack and group of `group_size` elves, collecting all anomalies
+/// instead of stopping at the first. Invalid items are left out of the group checks.
+fn validate(input: &[String], group_size: usize) -> Diagnostics {
+    let mut anomalies = Vec::new();
+    let mut rucksacks = Vec::new();
+
+    for (index, line) in input.iter().enumerate() {
+        let items = line.chars().collect::<Vec<_>>();
+        if !items.len().is_multiple_of(2) {
+            anomalies.push(Anomaly::OddLength {
+                line: index + 1,
+                length: items.len(),
+            });
+        }
+
+        // Invalid items are reported once here, and left out of every set.
+        let mut rucksack = ItemSet::default();
+        for &item in &items {
+            if rucksack.insert(item).is_err() {
+                anomalies.push(Anomaly::InvalidItem {
+                    line: index + 1,
+                    item,
+                });
+            }
+        }
+
+        if items.len().is_multiple_of(2) {
+            let valid_items = |items: &[char]| {
+                items.iter().fold(ItemSet::default(), |mut set, &item| {
+                    set.insert(item).ok();
+                    set
+                })
+            };
+            let (first_half, second_half) = items.split_at(items.len() / 2);
+            let shared = valid_items(first_half).intersection(valid_items(second_half));
+
+            match shared.len() {
+                0 => anomalies.push(Anomaly::NoSharedItem { line: index + 1 }),
+                1 => {}
+                _ => anomalies.push(Anomaly::MultipleSharedItems {
+                    line: index + 1,
+                    items: shared,
+                }),
+            }
+        }
+
+        rucksacks.push(rucksack);
+    }
+
+    if group_size == 0 {
+        anomalies.push(Anomaly::ZeroGroupSize);
+
+        return Diagnostics {
+            rucksacks: input.len(),
+            groups: 0,
+            anomalies,
+        };
+    }
+
+    let groups = rucksacks.chunks(group_size).collect::<Vec<_>>();
+
+    for (index, group) in groups.iter().enumerate() {
+        if group.len() < group_size {
+            anomalies.push(Anomaly::IncompleteGroup {
+                group: index + 1,
+                size: group.len(),
+            });
+        }
+
+        let badges = group
+            .iter()
+            .fold(ItemSet::ALL, |common, &items| common.intersection(items));
+
+        match badges.len() {
+            0 => anomalies.push(Anomaly::NoBadge { group: index + 1 }),
+            1 => {}
+            _ => anomalies.push(Anomaly::MultipleBadges {
+                group: index + 1,
+                badges,
+            }),
+        }
+    }
+
+    Diagnostics {
+        rucksacks: input.len(),
+        groups: groups.len(),
+        anomalies,
+    }
+}
+
+fn part1(input: &[String]) -> Result<u32> {
+    priority_sum(&compartment_items(input)?, "Line")
+}
+
+fn part2(input: &[String], group_size: usize) -> Result<u32> {
+    priority_sum(&group_items(input, group_size)?, "Group")
+}
+
+pub fn solve(options: &Options) {
     let input: Vec<String> = read_input(Date::Day03).expect("failed to read input");
 
-    println!("Day 1 Part 1: {:?}", part1(&input));
-    println!("Day 1 Part 2: {:?}", part2(&input));
+    if options.validate {
+        let diagnostics = validate(&input, options.group_size);
+        println!("{}", diagnostics);
+
+        if !diagnostics.is_valid() {
+            return;
+        }
+    }
+
+    if options.common {
+        let compartments = compartment_items(&input).expect("Invalid rucksacks");
+        for (index, items) in compartments.iter().enumerate() {
+            println!("Day 3 rucksack {}: `{}`", index + 1, items);
+        }
+
+        let groups = group_items(&input, options.group_size).expect("Invalid rucksacks");
+        for (index, items) in groups.iter().enumerate() {
+            println!("Day 3 group {}: `{}`", index + 1, items);
+        }
+    }
+
+    println!(
+        "Day 1 Part 1: {:?}",
+        part1(&input).expect("Failed to find the misplaced items")
+    );
+    println!(
+        "Day 1 Part 2: {:?}",
+        part2(&input, options.group_size).expect("Failed to find the badges")
+    );
 }
 
 #[cfg(test)]
@@ -97,12 +390,119 @@ CrZsJsPPZsGzwwsLwLmpwMDw";
     #[test]
     fn day03_part1() {
         let actual = get_input();
-        assert_eq!(part1(&actual), 157);
+        assert_eq!(part1(&actual).unwrap(), 157);
     }
 
     #[test]
     fn day03_part2() {
         let actual = get_input();
-        assert_eq!(part2(&actual), 70);
+        assert_eq!(part2(&actual, 3).unwrap(), 70);
+    }
+
+    #[test]
+    fn day03_item_set() {
+        let first = "vJrwpWtwJgWr".parse::<ItemSet>().unwrap();
+        let second = "hcsFMMfFFhFp".parse::<ItemSet>().unwrap();
+
+        assert_eq!(first.len(), 8);
+        assert_eq!(first.to_string(), "gprtvwJW");
+        assert_eq!(first.intersection(second).to_string(), "p");
+        assert_eq!(first.intersection(second).single_priority().unwrap(), 16);
+        assert_eq!(ItemSet::ALL.len(), 52);
+        assert!("ab1".parse::<ItemSet>().is_err());
+    }
+
+    #[test]
+    fn day03_multibyte_items() {
+        // `é` takes two bytes, so these lines have an even number of bytes.
+        let error = part1(&["aéb".to_string()]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Line 1: rucksack has an odd number of items"
+        );
+
+        let error = part1(&["aébc".to_string()]).unwrap_err();
+        assert_eq!(format!("{:#}", error), "Line 1: Invalid item `é`");
+    }
+
+    #[test]
+    fn day03_group_sizes() {
+        let actual = get_input();
+
+        // Every elf in a group of two shares more than one item with the other.
+        let pairs = group_items(&actual, 2).unwrap();
+        assert_eq!(
+            pairs.iter().map(ItemSet::to_string).collect::<Vec<_>>(),
+            ["frsFM", "qvwBT", "GJZ"]
+        );
+        assert!(part2(&actual, 2).is_err());
+
+        // A single group of all six elves shares no item at all.
+        assert_eq!(group_items(&actual, 6).unwrap(), [ItemSet::default()]);
+        assert!(part2(&actual, 6).is_err());
+
+        assert!(group_items(&actual, 4).is_err());
+    }
+
+    #[test]
+    fn day03_validate() {
+        let actual = get_input();
+        let diagnostics = validate(&actual, 3);
+        assert!(diagnostics.is_valid());
+        assert_eq!(diagnostics.groups, 2);
+
+        let input = ["abcb", "aBc", "ab1b", "xyzx"].map(String::from);
+        let diagnostics = validate(&input, 2);
+        assert_eq!(
+            diagnostics.anomalies,
+            [
+                Anomaly::OddLength { line: 2, length: 3 },
+                Anomaly::InvalidItem { line: 3, item: '1' },
+                Anomaly::MultipleBadges {
+                    group: 1,
+                    badges: "ac".parse().unwrap()
+                },
+                Anomaly::NoBadge { group: 2 },
+            ]
+        );
+        assert_eq!(
+            diagnostics.to_string(),
+            "Checked 4 rucksacks in 2 groups: 4 anomalies
+Line 2: odd number of items (3)
+Line 3: invalid item `1`
+Group 1: more than one badge `ac`
+Group 2: no badge"
+        );
+
+        // Compartments that share no item or several items make `part1` fail.
+        let input = ["abcd", "aXYZ", "aQRS", "abab"].map(String::from);
+        let diagnostics = validate(&input, 4);
+        assert_eq!(
+            diagnostics.anomalies,
+            [
+                Anomaly::NoSharedItem { line: 1 },
+                Anomaly::NoSharedItem { line: 2 },
+                Anomaly::NoSharedItem { line: 3 },
+                Anomaly::MultipleSharedItems {
+                    line: 4,
+                    items: "ab".parse().unwrap()
+                },
+            ]
+        );
+        assert!(part1(&input).is_err());
+        assert!(!validate(&input[..3], 3).is_valid());
+
+        // An extra rucksack starts a group that `part2` cannot use.
+        let mut extra = actual.clone();
+        extra.push("aa".to_string());
+        assert_eq!(
+            validate(&extra, 3).anomalies,
+            [Anomaly::IncompleteGroup { group: 3, size: 1 }]
+        );
+        assert!(part2(&extra, 3).is_err());
+
+        let diagnostics = validate(&actual, 0);
+        assert_eq!(diagnostics.anomalies, [Anomaly::ZeroGroupSize]);
+        assert!(part2(&actual, 0).is_err());
     }
 }
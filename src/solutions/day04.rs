@@ -1,4 +1,4 @@
-use crate::utils::{date::Date, io::read_input};
+use crate::utils::{date::Date, interval::IntervalSet, io::read_input, options::Options};
 
 enum Part {
     Part1,
@@ -68,6 +68,155 @@ fn overlapping(pairs: &[Pair], part: Part) -> i32 {
         .count() as i32
 }
 
+/// Gets the assignments of every elf in every pair as inclusive section ranges.
+fn assignments(pairs: &[Pair]) -> impl Iterator<Item = (u32, u32)> + '_ {
+    pairs
+        .iter()
+        .flat_map(|pair| [pair.first, pair.second])
+        .map(|interval| (interval.min, interval.max))
+}
+
+/// Gets the sections between the lowest and the highest assigned section
+/// that no elf is assigned to.
+fn uncovered_sections(pairs: &[Pair]) -> IntervalSet<u32> {
+    let covered = IntervalSet::merge(assignments(pairs));
+
+    match covered.span() {
+        Some(span) => IntervalSet::merge([span]).difference(&covered),
+        None => IntervalSet::default(),
+    }
+}
+
+/// Gets the sections that at least `count` elves across all pairs are assigned to.
+fn sections_covered_by(pairs: &[Pair], count: usize) -> IntervalSet<u32> {
+    IntervalSet::covered_at_least(assignments(pairs), count)
+}
+
+/// An elf, identified by the 1-based line of its pair and its 1-based position in the pair.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Elf {
+    line: usize,
+    position: usize,
+}
+
+/// Overlaps and containments between the assignments of all elves in all pairs.
+/// Elves are nodes identified by their index, which is `2 * line index + position index`.
+struct OverlapGraph {
+    elves: Vec<(Elf, Interval)>,
+    /// The elves each elf shares at least one section with.
+    overlaps: Vec<Vec<usize>>,
+    /// The elves whose assignment lies entirely within each elf's assignment.
+    contains: Vec<Vec<usize>>,
+    /// The largest number of elves assigned to one section.
+    max_depth: usize,
+}
+
+impl OverlapGraph {
+    /// Builds the graph with a sweep over the assignments in order of their first section.
+    /// Only assignments still active when another starts overlap it, so the work
+    /// done is proportional to the number of elves plus the number of overlaps.
+    fn new(pairs: &[Pair]) -> Self {
+        let elves = pairs
+            .iter()
+            .enumerate()
+            .flat_map(|(index, pair)| {
+                [pair.first, pair.second].into_iter().enumerate().map(
+                    move |(position, interval)| {
+                        let elf = Elf {
+                            line: index + 1,
+                            position: position + 1,
+                        };
+                        (elf, interval)
+                    },
+                )
+            })
+            .collect::<Vec<_>>();
+
+        // Sort by start, with longer assignments first, so that every assignment
+        // comes after all the assignments that contain it.
+        let mut order = (0..elves.len()).collect::<Vec<_>>();
+        order.sort_by_key(|&id| (elves[id].1.min, std::cmp::Reverse(elves[id].1.max)));
+
+        let mut overlaps = vec![Vec::new(); elves.len()];
+        let mut contains = vec![Vec::new(); elves.len()];
+        let mut active: Vec<usize> = Vec::new();
+        let mut max_depth = 0;
+
+        for id in order {
+            let interval = elves[id].1;
+            active.retain(|&other| elves[other].1.max >= interval.min);
+
+            for &other in &active {
+                let other_interval = elves[other].1;
+
+                overlaps[id].push(other);
+                overlaps[other].push(id);
+
+                if other_interval.includes(&interval) {
+                    contains[other].push(id);
+                }
+                if interval.includes(&other_interval) {
+                    contains[id].push(other);
+                }
+            }
+
+            active.push(id);
+            max_depth = max_depth.max(active.len());
+        }
+
+        Self {
+            elves,
+            overlaps,
+            contains,
+            max_depth,
+        }
+    }
+
+    /// Gets the number of pairs of elves, across all lines, that share a section.
+    fn overlap_count(&self) -> usize {
+        self.overlaps.iter().map(Vec::len).sum::<usize>() / 2
+    }
+
+    /// Gets the number of times one elf's assignment lies within another's.
+    fn containment_count(&self) -> usize {
+        self.contains.iter().map(Vec::len).sum()
+    }
+
+    /// Gets the largest number of elves assigned to one section and the sections they share.
+    fn most_overlapped_sections(&self) -> (usize, IntervalSet<u32>) {
+        if self.max_depth == 0 {
+            return (0, IntervalSet::default());
+        }
+
+        let sections = IntervalSet::covered_at_least(
+            self.elves
+                .iter()
+                .map(|(_, interval)| (interval.min, interval.max)),
+            self.max_depth,
+        );
+
+        (self.max_depth, sections)
+    }
+
+    /// Gets the largest group of elves that all overlap each other.
+    ///
+    /// Assignments that overlap pairwise always share a section, so this is
+    /// every elf assigned to one of the most overlapped sections.
+    fn largest_overlapping_group(&self) -> Vec<usize> {
+        let (_, sections) = self.most_overlapped_sections();
+
+        match sections.span() {
+            Some((section, _)) => (0..self.elves.len())
+                .filter(|&id| {
+                    let interval = self.elves[id].1;
+                    interval.min <= section && section <= interval.max
+                })
+                .collect(),
+            None => vec![],
+        }
+    }
+}
+
 fn part1(input: &[String]) -> i32 {
     let pairs = pairs(input);
     overlapping(&pairs, Part::Part1)
@@ -78,13 +227,58 @@ fn part2(input: &[String]) -> i32 {
     overlapping(&pairs, Part::Part2)
 }
 
-pub fn solve() {
+pub fn solve(options: &Options) {
     let input: Vec<String> = read_input(Date::Day04)
         .expect("failed to read input")
         .into_iter()
         .filter(|line: &String| !line.is_empty())
         .collect();
 
+    if let Some(count) = options.coverage {
+        let pairs = pairs(&input);
+
+        let uncovered = uncovered_sections(&pairs);
+        println!(
+            "Day 4 sections covered by no elf: {} ({})",
+            uncovered.coverage(),
+            uncovered
+        );
+
+        let covered = sections_covered_by(&pairs, count);
+        println!(
+            "Day 4 sections covered by {} or more elves: {} ({})",
+            count,
+            covered.coverage(),
+            covered
+        );
+    }
+
+    if options.overlaps {
+        let graph = OverlapGraph::new(&pairs(&input));
+        println!(
+            "Day 4 {} elves, {} overlaps, {} containments",
+            graph.elves.len(),
+            graph.overlap_count(),
+            graph.containment_count()
+        );
+
+        let (depth, sections) = graph.most_overlapped_sections();
+        println!(
+            "Day 4 most overlapped sections: {} ({} elves)",
+            sections, depth
+        );
+
+        let group = graph.largest_overlapping_group();
+        println!("Day 4 largest overlapping group: {} elves", group.len());
+        for id in group {
+            let (elf, interval) = graph.elves[id];
+            println!(
+                "Day 4 line {} elf {}: {}-{}",
+                elf.line, elf.position, interval.min, interval.max
+            );
+        }
+    }
+
     println!("Day 1 Part 1: {:?}", part1(&input));
     println!("Day 1 Part 2: {:?}", part2(&input));
 }
@@ -119,4 +313,48 @@ mod tests {
         let actual = get_input();
         assert_eq!(part2(&actual), 4);
     }
+
+    #[test]
+    fn day04_coverage() {
+        let actual = get_input();
+        let pairs = pairs(&actual);
+
+        assert!(uncovered_sections(&pairs).is_empty());
+        assert_eq!(sections_covered_by(&pairs, 3).to_string(), "2-8");
+        assert_eq!(sections_covered_by(&pairs, 5).to_string(), "3-7");
+        assert_eq!(sections_covered_by(&pairs, 7).to_string(), "4-6");
+        assert!(sections_covered_by(&pairs, 9).is_empty());
+
+        let gaps = super::pairs(&["1-2,5-6".to_string(), "9-9,4-4".to_string()]);
+        assert_eq!(uncovered_sections(&gaps).to_string(), "3-3,7-8");
+    }
+
+    #[test]
+    fn day04_overlap_graph() {
+        let actual = get_input();
+        let graph = OverlapGraph::new(&pairs(&actual));
+
+        assert_eq!(graph.overlap_count(), 49);
+        assert_eq!(graph.containment_count(), 29);
+        assert_eq!(graph.overlaps[6].len(), 11);
+
+        let mut contained = graph.contains[6].clone();
+        contained.sort();
+        assert_eq!(contained, [0, 1, 2, 3, 4, 7, 8, 9, 10, 11]);
+
+        let (depth, sections) = graph.most_overlapped_sections();
+        assert_eq!(depth, 8);
+        assert_eq!(sections.to_string(), "6-6");
+        assert_eq!(
+            graph.largest_overlapping_group(),
+            [1, 4, 6, 7, 8, 9, 10, 11]
+        );
+        assert_eq!(
+            graph.elves[3].0,
+            Elf {
+                line: 2,
+                position: 2
+            }
+        );
+    }
 }
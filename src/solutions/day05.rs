@@ -1,134 +1,515 @@
-use std::array::from_fn;
+use std::{fmt::Display, fs::write, str::FromStr, thread::sleep, time::Duration};
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use itertools::Itertools;
+
+use crate::utils::{io::read_input_file_or, options::Options};
+
+/// A crane model, defining how a number of crates are moved between two stacks.
+trait Crane {
+    fn name(&self) -> String;
+
+    fn move_crates(
+        &self,
+        crates: &mut Crates,
+        from: usize,
+        to: usize,
+        quantity: usize,
+    ) -> Result<()>;
+}
+
+/// Moves one crate at a time, as in part 1.
+struct CrateMover9000;
+
+impl Crane for CrateMover9000 {
+    fn name(&self) -> String {
+        "CrateMover 9000".to_string()
+    }
+
+    fn move_crates(
+        &self,
+        crates: &mut Crates,
+        from: usize,
+        to: usize,
+        quantity: usize,
+    ) -> Result<()> {
+        crates.validate_move(from, to, quantity)?;
+
+        for _ in 0..quantity {
+            crates.move_one(from, to)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Moves all crates at once, keeping their order, as in part 2.
+struct CrateMover9001;
+
+impl Crane for CrateMover9001 {
+    fn name(&self) -> String {
+        "CrateMover 9001".to_string()
+    }
+
+    fn move_crates(
+        &self,
+        crates: &mut Crates,
+        from: usize,
+        to: usize,
+        quantity: usize,
+    ) -> Result<()> {
+        crates.move_many(from, to, quantity)
+    }
+}
+
+/// Moves all crates at once, but flips the chunk upside down on the way.
+struct ReversedChunk;
+
+impl Crane for ReversedChunk {
+    fn name(&self) -> String {
+        "Reversed chunk".to_string()
+    }
+
+    fn move_crates(
+        &self,
+        crates: &mut Crates,
+        from: usize,
+        to: usize,
+        quantity: usize,
+    ) -> Result<()> {
+        crates.move_many(from, to, quantity)?;
+
+        let length = crates.stacks[to].len();
+        crates.stacks[to][length - quantity..].reverse();
+
+        Ok(())
+    }
+}
+
+/// Moves all crates at once like the CrateMover 9001,
+/// but splits moves larger than its capacity into several smaller moves.
+struct LimitedCapacity {
+    capacity: usize,
+}
+
+impl Crane for LimitedCapacity {
+    fn name(&self) -> String {
+        format!("Limited capacity ({})", self.capacity)
+    }
+
+    fn move_crates(
+        &self,
+        crates: &mut Crates,
+        from: usize,
+        to: usize,
+        quantity: usize,
+    ) -> Result<()> {
+        ensure!(self.capacity > 0, "The crane cannot lift any crates");
+        crates.validate_move(from, to, quantity)?;
+
+        let mut remaining = quantity;
+
+        while remaining > 0 {
+            let chunk = remaining.min(self.capacity);
+            crates.move_many(from, to, chunk)?;
+            remaining -= chunk;
+        }
+
+        Ok(())
+    }
+}
+
+/// The crane models that can be selected from the command line.
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum CraneModel {
+    #[value(name = "9000")]
+    CrateMover9000,
+    #[value(name = "9001")]
+    CrateMover9001,
+    ReversedChunk,
+    LimitedCapacity,
+}
+
+impl CraneModel {
+    fn crane(self, capacity: usize) -> Box<dyn Crane> {
+        match self {
+            CraneModel::CrateMover9000 => Box::new(CrateMover9000),
+            CraneModel::CrateMover9001 => Box::new(CrateMover9001),
+            CraneModel::ReversedChunk => Box::new(ReversedChunk),
+            CraneModel::LimitedCapacity => Box::new(LimitedCapacity { capacity }),
+        }
+    }
+}
+
+/// Shown in place of the top crate of a stack that has no crates left.
+const EMPTY_STACK: &str = "_";
+
+type Crate = String;
 
 #[derive(Clone)]
 struct Instruction {
-    quantity: u8,
-    from: u8,
-    to: u8,
+    quantity: usize,
+    from: usize,
+    to: usize,
 }
 
-impl Instruction {
-    fn parse(input: &str) -> Vec<Self> {
-        let mut instruction = input
-            .split_whitespace()
-            .skip(1)
-            .step_by(2)
-            .map(|num| num.parse().expect("Failed to parse number"));
-
-        let mut instructions = Vec::new();
-
-        while let (Some(quantity), Some(from), Some(to)) =
-            (instruction.next(), instruction.next(), instruction.next())
-        {
-            instructions.push(Instruction { quantity, from, to });
-        }
+impl FromStr for Instruction {
+    type Err = anyhow::Error;
+
+    /// Parses an instruction like `move 1 from 2 to 1`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let words = s.split_whitespace().collect::<Vec<_>>();
+
+        let [_, quantity, _, from, _, to] = words[..] else {
+            bail!("Invalid instruction `{}`", s);
+        };
 
-        instructions
+        let instruction = Instruction {
+            quantity: quantity.parse()?,
+            from: from.parse()?,
+            to: to.parse()?,
+        };
+
+        ensure!(
+            instruction.from > 0 && instruction.to > 0,
+            "Stacks are numbered from 1"
+        );
+
+        Ok(instruction)
+    }
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "move {} from {} to {}",
+            self.quantity, self.from, self.to
+        )
+    }
+}
+
+impl Instruction {
+    fn parse(input: &str) -> Result<Vec<Self>> {
+        input
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.parse())
+            .collect()
     }
 }
 
 #[derive(Clone)]
-struct Crates<const STACKS_COUNT: usize> {
-    stacks: [Vec<char>; STACKS_COUNT],
+struct Crates {
+    stacks: Vec<Vec<Crate>>,
+}
+
+/// Finds the character positions spanned by each stack number in the footer line.
+/// The footer must number the stacks `1` to `n` in order.
+fn stack_columns(footer: &str) -> Result<Vec<(usize, usize)>> {
+    let mut columns = Vec::new();
+    let mut start = None;
+    let mut number = String::new();
+
+    for (position, character) in footer.chars().chain([' ']).enumerate() {
+        match (start, character.is_whitespace()) {
+            (None, false) => {
+                start = Some(position);
+                number.push(character);
+            }
+            (Some(_), false) => number.push(character),
+            (Some(column_start), true) => {
+                let expected = columns.len() + 1;
+                ensure!(
+                    number.parse::<usize>().ok() == Some(expected),
+                    "Expected stack number {} in the footer, found `{}`",
+                    expected,
+                    number
+                );
+
+                columns.push((column_start, position - 1));
+                start = None;
+                number.clear();
+            }
+            (None, true) => {}
+        }
+    }
+
+    ensure!(!columns.is_empty(), "No stack numbers");
+
+    Ok(columns)
+}
+
+/// Finds every bracketed crate label in a line, along with the character positions
+/// of its opening and closing brackets.
+fn bracketed_crates(line: &str) -> Result<Vec<(usize, usize, Crate)>> {
+    let mut crates = Vec::new();
+    let mut open: Option<(usize, String)> = None;
+
+    for (position, character) in line.chars().enumerate() {
+        match (&mut open, character) {
+            (None, '[') => open = Some((position, String::new())),
+            (None, character) if character.is_whitespace() => {}
+            (None, character) => bail!("Unexpected `{}` outside of a crate", character),
+            (Some((start, label)), ']') => {
+                ensure!(!label.is_empty(), "Empty crate label");
+                crates.push((*start, position, label.clone()));
+                open = None;
+            }
+            (Some((_, label)), character) => label.push(character),
+        }
+    }
+
+    ensure!(open.is_none(), "Unclosed crate");
+
+    Ok(crates)
 }
 
-impl<const STACKS_COUNT: usize> Crates<STACKS_COUNT> {
-    /// Creates new crates from a string of characters.
-    /// The string is split into `STACKS_COUNT` stacks.
-    fn parse(input: &str) -> Self {
-        let mut stacks = from_fn(|_| Vec::new());
+impl Crates {
+    /// Creates new crates from the drawing of the stacks.
+    /// The number of stacks is given by the numbered footer line, and each crate
+    /// belongs to the stack whose number is below it.
+    fn parse(input: &str) -> Result<Self> {
+        let mut lines = input.lines().rev();
+        let footer = lines.next().ok_or_else(|| anyhow!("No stack numbers"))?;
+        let columns = stack_columns(footer)?;
+
+        let mut stacks = vec![Vec::new(); columns.len()];
 
-        for line in input.lines().rev() {
-            let characters = line.chars().skip(1).step_by(4);
+        for line in lines {
+            for (start, end, label) in bracketed_crates(line)? {
+                let index = columns
+                    .iter()
+                    .position(|&(column_start, column_end)| {
+                        column_start <= end && start <= column_end
+                    })
+                    .ok_or_else(|| anyhow!("Crate `[{}]` is not above a stack number", label))?;
 
-            for (i, character) in characters.enumerate() {
-                if let character @ 'A'..='Z' = character {
-                    stacks[i].push(character)
-                }
+                stacks[index].push(label);
             }
         }
 
-        Self { stacks }
+        Ok(Self { stacks })
     }
 
-    /// Pops off the top crate from each stack in the collection of crates.
-    fn top_crates(&mut self) -> String {
+    /// Gets the top crate from each stack in the collection of crates.
+    /// Empty stacks are shown as [`EMPTY_STACK`].
+    fn top_crates(&self) -> String {
         self.stacks
-            .iter_mut()
-            .map(|stack| stack.pop().expect("No crates left"))
+            .iter()
+            .map(|stack| stack.last().map(String::as_str).unwrap_or(EMPTY_STACK))
             .collect()
     }
 
-    fn move_one(&mut self, from: usize, to: usize) {
+    /// Checks that both stacks exist and that there are enough crates to move.
+    fn validate_move(&self, from: usize, to: usize, quantity: usize) -> Result<()> {
+        for index in [from, to] {
+            ensure!(
+                index < self.stacks.len(),
+                "Stack {} does not exist",
+                index + 1
+            );
+        }
+
+        ensure!(
+            self.stacks[from].len() >= quantity,
+            "Cannot move {} crate(s) from stack {} holding {}",
+            quantity,
+            from + 1,
+            self.stacks[from].len()
+        );
+
+        Ok(())
+    }
+
+    fn move_one(&mut self, from: usize, to: usize) -> Result<()> {
+        self.validate_move(from, to, 1)?;
+
         let value = self.stacks[from].pop().expect("No crates left");
         self.stacks[to].push(value);
+
+        Ok(())
     }
 
-    fn move_many(&mut self, from: usize, to: usize, quantity: usize) {
-        let mut crates_to_move = self.stacks[from].split_off(self.stacks[from].len() - quantity);
+    fn move_many(&mut self, from: usize, to: usize, quantity: usize) -> Result<()> {
+        self.validate_move(from, to, quantity)?;
+
+        let remaining = self.stacks[from].len() - quantity;
+        let mut crates_to_move = self.stacks[from].split_off(remaining);
         self.stacks[to].append(&mut crates_to_move);
+
+        Ok(())
     }
 }
 
-struct CrateContainer<const STACKS_COUNT: usize> {
-    crates: Crates<STACKS_COUNT>,
+impl Display for Crates {
+    /// Draws the stacks in the same format as the puzzle input, including the numbered footer,
+    /// so that the result can be parsed again by [`Crates::parse`].
+    /// Columns are widened to fit the longest crate label and stack number.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let height = self.stacks.iter().map(Vec::len).max().unwrap_or(0);
+        let width = self
+            .stacks
+            .iter()
+            .flatten()
+            .map(|label| label.chars().count() + 2)
+            .chain([3, self.stacks.len().to_string().len()])
+            .max()
+            .unwrap_or(3);
+
+        for row in (0..height).rev() {
+            let line = self
+                .stacks
+                .iter()
+                .map(|stack| match stack.get(row) {
+                    Some(label) => format!("{:<width$}", format!("[{}]", label)),
+                    None => " ".repeat(width),
+                })
+                .join(" ");
+
+            writeln!(f, "{}", line)?;
+        }
+
+        let footer = (1..=self.stacks.len())
+            .map(|number| {
+                let number = number.to_string();
+                let padding = (width - number.len()) / 2;
+                format!("{:<width$}", format!("{}{}", " ".repeat(padding), number))
+            })
+            .join(" ");
+
+        write!(f, "{}", footer)
+    }
+}
+
+struct CrateContainer {
+    crates: Crates,
     instructions: Vec<Instruction>,
 }
 
-fn parse_input<const STACKS_COUNT: usize>(input: &str) -> CrateContainer<STACKS_COUNT> {
-    let (crates_input, instruction_input) = input.split_once("\n\n").expect("Invalid input");
-    let instructions = Instruction::parse(instruction_input);
-    let crates = Crates::parse(crates_input);
+fn parse_input(input: &str) -> Result<CrateContainer> {
+    let (crates_input, instruction_input) = input
+        .split_once("\n\n")
+        .ok_or_else(|| anyhow!("Invalid input"))?;
+    let instructions = Instruction::parse(instruction_input)?;
+    let crates = Crates::parse(crates_input)?;
 
-    CrateContainer {
+    Ok(CrateContainer {
         crates,
         instructions,
-    }
+    })
+}
+
+/// Carries out a single instruction with the given crane.
+fn apply(crates: &mut Crates, instruction: &Instruction, crane: &dyn Crane) -> Result<()> {
+    crane.move_crates(
+        crates,
+        instruction.from - 1,
+        instruction.to - 1,
+        instruction.quantity,
+    )
 }
 
-fn part1<const STACKS_COUNT: usize>(container: &CrateContainer<STACKS_COUNT>) -> String {
-    // We clone the borrowed `crates` to avoid borrowing it mutably.
-    // This way, part1 and part2 can be called on the same crates.
+/// Renders the crates before the first instruction and after every instruction.
+fn frames(container: &CrateContainer, crane: &dyn Crane) -> Result<Vec<String>> {
     let mut crates = container.crates.clone();
-    let instructions = &container.instructions;
+    let mut frames = vec![crates.to_string()];
 
-    for instruction in instructions {
-        for _ in 0..instruction.quantity {
-            let from = instruction.from as usize - 1;
-            let to = instruction.to as usize - 1;
+    for (index, instruction) in container.instructions.iter().enumerate() {
+        apply(&mut crates, instruction, crane)
+            .with_context(|| format!("Instruction {}", index + 1))?;
+        frames.push(crates.to_string());
+    }
 
-            crates.move_one(from, to);
-        }
+    Ok(frames)
+}
+
+/// Replays the instructions in the terminal, redrawing the crates after each one.
+fn animate(container: &CrateContainer, crane: &dyn Crane, delay: Duration) -> Result<()> {
+    const CLEAR_SCREEN: &str = "\x1B[2J\x1B[H";
+
+    let captions = [crane.name()]
+        .into_iter()
+        .chain(container.instructions.iter().map(Instruction::to_string));
+
+    for (caption, frame) in captions.zip(frames(container, crane)?) {
+        println!("{}{}\n\n{}", CLEAR_SCREEN, caption, frame);
+        sleep(delay);
     }
 
-    crates.top_crates()
+    Ok(())
 }
 
-fn part2<const STACKS_COUNT: usize>(container: &CrateContainer<STACKS_COUNT>) -> String {
-    // We clone the borrowed `crates` to avoid borrowing it mutably.
-    // This way, part1 and part2 can be called on the same crates.
+fn rearrange(container: &CrateContainer, crane: &dyn Crane) -> Result<Crates> {
+    // The crane works on a copy of the crates, so the same container
+    // can be rearranged again by a different crane.
     let mut crates = container.crates.clone();
-    let instructions = &container.instructions;
-
-    for instruction in instructions {
-        let (quantity, from, to) = (
-            instruction.quantity as usize,
-            instruction.from as usize - 1,
-            instruction.to as usize - 1,
-        );
 
-        crates.move_many(from, to, quantity);
+    for (index, instruction) in container.instructions.iter().enumerate() {
+        apply(&mut crates, instruction, crane)
+            .with_context(|| format!("Instruction {}", index + 1))?;
     }
 
-    crates.top_crates()
+    Ok(crates)
 }
 
-pub fn solve() {
-    let input = include_str!("../../input/day05.txt");
-    let container = parse_input::<9>(input);
+fn part1(container: &CrateContainer) -> Result<String> {
+    Ok(rearrange(container, &CrateMover9000)?.top_crates())
+}
+
+fn part2(container: &CrateContainer) -> Result<String> {
+    Ok(rearrange(container, &CrateMover9001)?.top_crates())
+}
+
+pub fn solve(options: &Options) {
+    let input = read_input_file_or(&options.input, include_str!("../../input/day05.txt"))
+        .expect("Failed to read input");
+    let container = parse_input(&input).expect("Failed to parse input");
+
+    let cranes: Vec<Box<dyn Crane>> = match options.crane {
+        Some(model) => vec![model.crane(options.capacity)],
+        None => vec![Box::new(CrateMover9000), Box::new(CrateMover9001)],
+    };
+
+    for crane in &cranes {
+        if options.animate {
+            animate(
+                &container,
+                crane.as_ref(),
+                Duration::from_millis(options.delay),
+            )
+            .expect("Failed to move crates");
+        } else if options.frames {
+            for frame in frames(&container, crane.as_ref()).expect("Failed to move crates") {
+                println!("{}\n", frame);
+            }
+        }
+    }
+
+    if let (Some(path), Some(crane)) = (&options.save, cranes.last()) {
+        let crates = rearrange(&container, crane.as_ref()).expect("Failed to move crates");
 
-    println!("Day 1 Part 1: {:?}", part1::<9>(&container));
-    println!("Day 1 Part 2: {:?}", part2::<9>(&container));
+        // Leave the instructions empty, so that new ones can be appended to the file.
+        write(path, format!("{}\n\n", crates)).expect("Failed to save crates");
+    }
+
+    if options.crane.is_some() {
+        for crane in &cranes {
+            let crates = rearrange(&container, crane.as_ref()).expect("Failed to move crates");
+            println!("Day 5 {}: {:?}", crane.name(), crates.top_crates());
+        }
+
+        return;
+    }
+
+    println!(
+        "Day 1 Part 1: {:?}",
+        part1(&container).expect("Failed to move crates")
+    );
+    println!(
+        "Day 1 Part 2: {:?}",
+        part2(&container).expect("Failed to move crates")
+    );
 }
 
 #[cfg(test)]
@@ -147,13 +528,122 @@ move 1 from 1 to 2";
 
     #[test]
     fn day05_part1() {
-        let container = parse_input::<3>(INPUT);
-        assert_eq!(part1(&container), "CMZ");
+        let container = parse_input(INPUT).unwrap();
+        assert_eq!(part1(&container).unwrap(), "CMZ");
     }
 
     #[test]
     fn day05_part2() {
-        let container = parse_input::<3>(INPUT);
-        assert_eq!(part2(&container), "MCD");
+        let container = parse_input(INPUT).unwrap();
+        assert_eq!(part2(&container).unwrap(), "MCD");
+    }
+
+    #[test]
+    fn day05_stacks_count_from_footer() {
+        let input = "[A]                                 [J]
+[B] [C] [D] [E] [F] [G] [H] [I] [K] [L]
+ 1   2   3   4   5   6   7   8   9   10
+
+move 1 from 10 to 1";
+        let container = parse_input(input).unwrap();
+        assert_eq!(container.crates.stacks.len(), 10);
+        assert_eq!(part1(&container).unwrap(), "JCDEFGHIKL");
+    }
+
+    #[test]
+    fn day05_empty_stacks_and_long_labels() {
+        let input = "[ab]
+[Cd] [EF]
+ 1    2  
+
+move 2 from 1 to 2";
+        let container = parse_input(input).unwrap();
+        assert_eq!(container.crates.stacks, [vec!["Cd", "ab"], vec!["EF"]]);
+        assert_eq!(part1(&container).unwrap(), "_Cd");
+        assert_eq!(part2(&container).unwrap(), "_ab");
+    }
+
+    #[test]
+    fn day05_invalid_footer() {
+        let error = |input: &str| match parse_input(input) {
+            Ok(_) => panic!("Expected `{}` to be rejected", input),
+            Err(error) => error.root_cause().to_string(),
+        };
+
+        // Without a footer, the bottom row of crates would be read as the footer.
+        assert_eq!(
+            error("[A] [B]\n[C] [D]\n\nmove 1 from 1 to 2"),
+            "Expected stack number 1 in the footer, found `[C]`"
+        );
+        assert_eq!(
+            error("[A] [B]\n x   y \n\nmove 1 from 1 to 2"),
+            "Expected stack number 1 in the footer, found `x`"
+        );
+        assert_eq!(
+            error("[A] [B]\n 1   3 \n\nmove 1 from 1 to 2"),
+            "Expected stack number 2 in the footer, found `3`"
+        );
+    }
+
+    #[test]
+    fn day05_move_from_empty_stack() {
+        let input = "[A]
+ 1   2
+
+move 2 from 1 to 2";
+        let container = parse_input(input).unwrap();
+        let error = part2(&container).unwrap_err();
+        assert_eq!(
+            error.root_cause().to_string(),
+            "Cannot move 2 crate(s) from stack 1 holding 1"
+        );
+    }
+
+    #[test]
+    fn day05_frames() {
+        let container = parse_input(INPUT).unwrap();
+        let frames = frames(&container, &CrateMover9001).unwrap();
+
+        assert_eq!(frames.len(), 5);
+        assert_eq!(frames[0], INPUT.split_once("\n\n").unwrap().0);
+        assert_eq!(
+            frames[2],
+            "        [D]
+        [N]
+    [C] [Z]
+    [M] [P]
+ 1   2   3 "
+        );
+    }
+
+    #[test]
+    fn day05_cranes() {
+        let container = parse_input(INPUT).unwrap();
+        let top_crates = |crane: &dyn Crane| rearrange(&container, crane).unwrap().top_crates();
+
+        assert_eq!(top_crates(&ReversedChunk), "CMZ");
+        assert_eq!(top_crates(&LimitedCapacity { capacity: 1 }), "CMZ");
+        assert_eq!(top_crates(&LimitedCapacity { capacity: 3 }), "MCD");
+        assert_eq!(top_crates(&LimitedCapacity { capacity: 2 }), "MCZ");
+    }
+
+    #[test]
+    fn day05_round_trip() {
+        let drawings = [
+            INPUT.split_once("\n\n").unwrap().0,
+            "[ab]     \n[Cd] [EF]\n 1    2  ",
+            "[A]                                    \n\
+             [B] [C] [D] [E] [F] [G] [H] [I] [J] [K]\n \
+             1   2   3   4   5   6   7   8   9  10 ",
+        ];
+
+        for drawing in drawings {
+            let crates = Crates::parse(drawing).unwrap();
+            assert_eq!(crates.to_string(), drawing);
+            assert_eq!(
+                Crates::parse(&crates.to_string()).unwrap().stacks,
+                crates.stacks
+            );
+        }
     }
 }
@@ -1,32 +1,181 @@
-fn marker<const CHUNK_SIZE: usize>(input: &str) -> usize {
-    input
-        .as_bytes()
-        .windows(CHUNK_SIZE)
-        .position(|chunk| {
-            let mut chunk_range = 0..(CHUNK_SIZE - 1);
-
-            !chunk_range.any(|i| {
-                let mut rest_range = (i + 1)..CHUNK_SIZE;
-                rest_range.any(|j| chunk[i] == chunk[j])
-            })
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{stdin, BufReader, Read},
+    path::Path,
+};
+
+use anyhow::{anyhow, ensure, Result};
+
+use crate::utils::options::Options;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MarkerKind {
+    StartOfPacket,
+    StartOfMessage,
+}
+
+impl MarkerKind {
+    fn window_size(&self) -> usize {
+        match self {
+            MarkerKind::StartOfPacket => 4,
+            MarkerKind::StartOfMessage => 14,
+        }
+    }
+}
+
+/// Detects markers, i.e. windows of `window_size` distinct bytes, in linear time.
+/// Bytes are fed one at a time, so the signal never needs to be in memory at once.
+struct MarkerDetector {
+    window_size: usize,
+    window: VecDeque<u8>,
+    counts: [usize; 256],
+    /// The number of byte values occurring more than once in the window.
+    duplicates: usize,
+    position: usize,
+}
+
+impl MarkerDetector {
+    fn new(window_size: usize) -> Result<Self> {
+        ensure!(window_size > 0, "The window size must be positive");
+
+        Ok(Self {
+            window_size,
+            window: VecDeque::with_capacity(window_size + 1),
+            counts: [0; 256],
+            duplicates: 0,
+            position: 0,
         })
-        .expect("No solution found")
-        + CHUNK_SIZE
+    }
+
+    /// Feeds the next byte of the signal, returning the number of bytes
+    /// processed so far if they end with a marker.
+    fn push(&mut self, byte: u8) -> Option<usize> {
+        self.position += 1;
+        self.window.push_back(byte);
+        self.counts[byte as usize] += 1;
+
+        if self.counts[byte as usize] == 2 {
+            self.duplicates += 1;
+        }
+
+        if self.window.len() > self.window_size {
+            let removed = self.window.pop_front().expect("Window is not empty") as usize;
+            self.counts[removed] -= 1;
+
+            if self.counts[removed] == 1 {
+                self.duplicates -= 1;
+            }
+        }
+
+        let is_marker = self.window.len() == self.window_size && self.duplicates == 0;
+        is_marker.then_some(self.position)
+    }
+}
+
+/// Line endings are not part of the signal, and are skipped before detecting markers.
+fn is_line_ending(byte: u8) -> bool {
+    byte == b'\n' || byte == b'\r'
+}
+
+/// Finds the end positions of all markers of the given size.
+fn markers(input: &str, window_size: usize) -> Result<Vec<usize>> {
+    let mut detector = MarkerDetector::new(window_size)?;
+
+    Ok(input
+        .bytes()
+        .filter(|&byte| !is_line_ending(byte))
+        .filter_map(|byte| detector.push(byte))
+        .collect())
+}
+
+/// Finds the end position of the first marker of the given size.
+fn marker(input: &str, window_size: usize) -> Result<usize> {
+    let mut detector = MarkerDetector::new(window_size)?;
+
+    input
+        .bytes()
+        .filter(|&byte| !is_line_ending(byte))
+        .find_map(|byte| detector.push(byte))
+        .ok_or_else(|| anyhow!("No marker of {} distinct characters found", window_size))
+}
+
+/// Reads a signal incrementally, reporting the first start-of-packet and
+/// start-of-message markers as soon as they occur.
+/// Stops reading once both markers are found, and never holds more than
+/// a buffer and a window of the signal in memory.
+fn stream_markers<R, F>(reader: R, mut report: F) -> Result<()>
+where
+    R: Read,
+    F: FnMut(MarkerKind, usize),
+{
+    let mut detectors = [MarkerKind::StartOfPacket, MarkerKind::StartOfMessage]
+        .into_iter()
+        .map(|kind| Ok((kind, MarkerDetector::new(kind.window_size())?)))
+        .collect::<Result<Vec<_>>>()?;
+
+    for byte in BufReader::new(reader).bytes() {
+        let byte = byte?;
+        if is_line_ending(byte) {
+            continue;
+        }
+
+        detectors.retain_mut(|(kind, detector)| match detector.push(byte) {
+            Some(position) => {
+                report(*kind, position);
+                false
+            }
+            None => true,
+        });
+
+        if detectors.is_empty() {
+            return Ok(());
+        }
+    }
+
+    let (kind, _) = &detectors[0];
+    Err(anyhow!("No {:?} marker found", kind))
 }
 
-fn part1(input: &str) -> usize {
-    marker::<4>(input)
+fn part1(input: &str) -> Result<usize> {
+    marker(input, 4)
 }
 
-fn part2(input: &str) -> usize {
-    marker::<14>(input)
+fn part2(input: &str) -> Result<usize> {
+    marker(input, 14)
 }
 
-pub fn solve() {
+pub fn solve(options: &Options) {
+    if let Some(path) = &options.input {
+        let reader: Box<dyn Read> = if path == Path::new("-") {
+            Box::new(stdin())
+        } else {
+            Box::new(File::open(path).expect("Failed to open input"))
+        };
+
+        stream_markers(reader, |kind, position| {
+            println!("Day 6 {:?} marker: {}", kind, position)
+        })
+        .expect("Failed to find markers");
+
+        return;
+    }
+
     let input = include_str!("../../input/day06.txt");
 
-    println!("Day 1 Part 1: {:?}", part1(input));
-    println!("Day 1 Part 2: {:?}", part2(input));
+    if let Some(window_size) = options.window {
+        let markers = markers(input, window_size).expect("Failed to find markers");
+        println!("Day 6 markers of size {}: {:?}", window_size, markers);
+    }
+
+    println!(
+        "Day 1 Part 1: {:?}",
+        part1(input).expect("Failed to find marker")
+    );
+    println!(
+        "Day 1 Part 2: {:?}",
+        part2(input).expect("Failed to find marker")
+    );
 }
 
 #[cfg(test)]
@@ -46,15 +195,15 @@ mod tests {
         let input5: TestCase = ("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw", 11);
 
         let (input, expected) = input1;
-        assert_eq!(part1(input), expected);
+        assert_eq!(part1(input).unwrap(), expected);
         let (input, expected) = input2;
-        assert_eq!(part1(input), expected);
+        assert_eq!(part1(input).unwrap(), expected);
         let (input, expected) = input3;
-        assert_eq!(part1(input), expected);
+        assert_eq!(part1(input).unwrap(), expected);
         let (input, expected) = input4;
-        assert_eq!(part1(input), expected);
+        assert_eq!(part1(input).unwrap(), expected);
         let (input, expected) = input5;
-        assert_eq!(part1(input), expected);
+        assert_eq!(part1(input).unwrap(), expected);
     }
 
     #[test]
@@ -66,14 +215,81 @@ mod tests {
         let input5: TestCase = ("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw", 26);
 
         let (input, expected) = input1;
-        assert_eq!(part2(input), expected);
+        assert_eq!(part2(input).unwrap(), expected);
         let (input, expected) = input2;
-        assert_eq!(part2(input), expected);
+        assert_eq!(part2(input).unwrap(), expected);
         let (input, expected) = input3;
-        assert_eq!(part2(input), expected);
+        assert_eq!(part2(input).unwrap(), expected);
         let (input, expected) = input4;
-        assert_eq!(part2(input), expected);
+        assert_eq!(part2(input).unwrap(), expected);
         let (input, expected) = input5;
-        assert_eq!(part2(input), expected);
+        assert_eq!(part2(input).unwrap(), expected);
+    }
+
+    #[test]
+    fn day06_all_markers() {
+        assert_eq!(markers("abcabd", 3).unwrap(), [3, 4, 5, 6]);
+        assert_eq!(markers("aabbaab", 2).unwrap(), [3, 5, 7]);
+        assert_eq!(markers("aaaa", 1).unwrap(), [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn day06_trailing_newline() {
+        assert_eq!(markers("abcabfrq\n", 4).unwrap(), [6, 7, 8]);
+        assert_eq!(markers("aabbaab\r\n", 2).unwrap(), [3, 5, 7]);
+        assert!(marker("frq\n", 4).is_err());
+        assert_eq!(part1("mjqjpqmgbljsphdztnvjfqwrcgsmlb\n").unwrap(), 7);
+    }
+
+    #[test]
+    fn day06_marker_not_found() {
+        assert!(marker("aaaa", 2).is_err());
+        assert!(marker("abc", 4).is_err());
+        assert!(marker("abc", 0).is_err());
+    }
+
+    #[test]
+    fn day06_stream_markers() {
+        // The reader never ends, so this only finishes if streaming stops after both markers.
+        let reader = "mjqjpqmgbljsphdztnvjfqwrcgsmlb"
+            .as_bytes()
+            .chain(std::io::repeat(b'a'));
+
+        let mut reported = Vec::new();
+        stream_markers(reader, |kind, position| reported.push((kind, position))).unwrap();
+
+        assert_eq!(
+            reported,
+            [
+                (MarkerKind::StartOfPacket, 7),
+                (MarkerKind::StartOfMessage, 19)
+            ]
+        );
+    }
+
+    #[test]
+    fn day06_stream_without_message_marker() {
+        let mut reported = Vec::new();
+        let result = stream_markers("abcdabcd".as_bytes(), |kind, position| {
+            reported.push((kind, position))
+        });
+
+        assert_eq!(reported, [(MarkerKind::StartOfPacket, 4)]);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "No StartOfMessage marker found"
+        );
+
+        // The final newline must not complete a marker.
+        let mut reported = Vec::new();
+        let result = stream_markers("abcabca\n".as_bytes(), |kind, position| {
+            reported.push((kind, position))
+        });
+
+        assert!(reported.is_empty());
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "No StartOfPacket marker found"
+        );
     }
 }
@@ -1,194 +1,407 @@
-use std::ops::AddAssign;
+use anyhow::{anyhow, bail, ensure, Context, Result};
 
-struct Tree<T>
-where
-    T: PartialEq,
-{
-    nodes: Vec<Node<T>>,
-    current_index: usize,
+use crate::utils::{
+    options::Options,
+    tree::{NodeId, Tree},
+};
+
+struct File {
+    name: String,
+    size: FileSize,
+}
+
+struct Directory {
+    label: String,
+    /// Total size of all files in this directory and its subdirectories.
+    size: FileSize,
+    files: Vec<File>,
+}
+
+impl Directory {
+    fn new(label: &str) -> Self {
+        Self {
+            label: label.to_string(),
+            size: 0,
+            files: vec![],
+        }
+    }
 }
 
-impl<T> Tree<T>
-where
-    T: Copy + PartialEq + AddAssign,
-{
-    fn new(nodes: Vec<Node<T>>) -> Self {
+/// The file system reconstructed from a terminal transcript,
+/// along with the directory the transcript is currently in.
+struct FileSystem {
+    tree: Tree<Directory>,
+    current_index: NodeId,
+}
+
+impl FileSystem {
+    fn new() -> Self {
+        let tree = Tree::new(Directory::new("/"));
+        let current_index = tree.root();
+
         Self {
-            nodes,
-            current_index: 0,
+            tree,
+            current_index,
         }
     }
 
-    fn insert(&mut self, node: Node<T>) {
-        self.nodes.push(node);
+    fn directory(&self, index: NodeId) -> &Directory {
+        self.tree.get(index).expect("Invalid directory")
     }
 
-    /// Gets the parent node at a given index.
-    fn parent(&self, index: usize) -> Option<usize> {
-        self.nodes[index].parent
+    fn directories(&self) -> impl Iterator<Item = &Directory> {
+        self.tree.iter().map(|(_, directory)| directory)
     }
 
-    /// Gets the child node at a given index with a target_label.
-    fn child(&self, target_label: &str) -> Option<usize> {
-        self.nodes[self.current_index]
-            .children
+    /// Inserts a new, empty directory as a child of the current directory.
+    fn insert_directory(&mut self, label: &str) -> NodeId {
+        self.tree.insert(self.current_index, Directory::new(label))
+    }
+
+    /// Gets the child of the current directory with a target_label.
+    fn child(&self, target_label: &str) -> Option<NodeId> {
+        self.tree
+            .children(self.current_index)
             .iter()
-            .find(|child_index| self.nodes[**child_index].label == target_label)
+            .find(|child_index| self.directory(**child_index).label == target_label)
             .copied()
     }
 
-    fn add_file(&mut self, index: usize, file_size: T) {
-        // Add the file size to the current node.
-        self.nodes[index].size += file_size;
+    fn add_file(&mut self, index: NodeId, file_name: &str, file_size: FileSize) {
+        let directory = self.tree.get_mut(index).expect("Invalid directory");
+        directory.files.push(File {
+            name: file_name.to_string(),
+            size: file_size,
+        });
+
+        // Add the file size to the directory and all of its parent directories.
+        let ancestors = self.tree.ancestors(index).collect::<Vec<_>>();
+        for index in std::iter::once(index).chain(ancestors) {
+            self.tree.get_mut(index).expect("Invalid directory").size += file_size;
+        }
+    }
+
+    /// Gets the absolute path of the directory at a given index, e.g. `/a/e`.
+    fn path(&self, index: NodeId) -> String {
+        let label = &self.directory(index).label;
 
-        // Recursively add the file size to the parent nodes.
-        if let Some(index) = self.nodes[index].parent {
-            self.add_file(index, file_size);
+        match self.tree.parent(index) {
+            Some(parent_index) if parent_index == self.tree.root() => format!("/{}", label),
+            Some(parent_index) => format!("{}/{}", self.path(parent_index), label),
+            None => label.clone(),
         }
     }
 
     fn go_to_root_directory(&mut self) {
-        self.current_index = 0;
+        self.current_index = self.tree.root();
     }
 
-    fn go_to_parent_directory(&mut self) {
-        match self.parent(self.current_index) {
-            Some(parent_index) => {
-                self.current_index = parent_index;
-            }
-            None => unreachable!("Invalid parent"),
-        };
+    fn go_to_parent_directory(&mut self) -> Result<()> {
+        let parent_index = self
+            .tree
+            .parent(self.current_index)
+            .ok_or_else(|| anyhow!("Cannot leave the root directory"))?;
+        self.current_index = parent_index;
+
+        Ok(())
     }
 
-    fn go_to_child_directory(&mut self, directory: &str) {
-        match self.child(directory) {
-            Some(child_index) => {
-                self.current_index = child_index;
-            }
-            None => unreachable!("Invalid child"),
-        };
+    fn go_to_child_directory(&mut self, directory: &str) -> Result<()> {
+        let child_index = self
+            .child(directory)
+            .ok_or_else(|| anyhow!("Unknown directory `{}`", directory))?;
+        self.current_index = child_index;
+
+        Ok(())
     }
 
-    fn go_to_directory(&mut self, directory: &str) {
+    fn go_to_directory(&mut self, directory: &str) -> Result<()> {
         match directory {
             "/" => self.go_to_root_directory(),
-            ".." => self.go_to_parent_directory(),
-            child_dir => self.go_to_child_directory(child_dir),
-        }
-    }
-}
+            ".." => self.go_to_parent_directory()?,
+            child_dir => self.go_to_child_directory(child_dir)?,
+        };
 
-struct Node<T>
-where
-    T: PartialEq,
-{
-    label: String,
-    size: T,
-    parent: Option<usize>,
-    children: Vec<usize>,
-}
+        Ok(())
+    }
 
-impl<T> Node<T>
-where
-    T: PartialEq,
-{
-    fn new(label: &str, size: T) -> Self {
-        Self {
-            label: label.to_string(),
-            size,
-            parent: None,
-            children: vec![],
+    /// Like [`FileSystem::go_to_directory`], but creates unknown child directories
+    /// instead of failing.
+    fn go_to_directory_or_create(&mut self, directory: &str) -> Result<()> {
+        if !matches!(directory, "/" | "..") && self.child(directory).is_none() {
+            self.insert_directory(directory);
         }
+
+        self.go_to_directory(directory)
     }
 }
 
 type Argument<'a> = &'a str;
-type FileSize = u32;
+type FileSize = u64;
+
+/// How to treat terminal transcripts that do not follow the puzzle's rules.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ParseMode {
+    /// Fail on the first malformed line, reporting its line number.
+    Strict,
+    /// Create directories on `cd` into unknown directories, ignore repeated
+    /// listings and skip lines that cannot be understood.
+    Lenient,
+}
 
 enum Line<'a> {
     ChangeDirectory(Argument<'a>),
+    List,
     Directory(Argument<'a>),
-    File(FileSize),
+    File(Argument<'a>, FileSize),
 }
 
-fn parse_line(line: &'_ str) -> Option<Line<'_>> {
+fn parse_line(line: &'_ str) -> Result<Line<'_>> {
     match line.split_once(' ') {
         Some(("$", command)) => match command.split_once(' ') {
-            Some(("cd", dir)) => Some(Line::ChangeDirectory(dir)),
-            // Ignore the `ls` command.
-            _ => None,
+            Some(("cd", dir)) => Ok(Line::ChangeDirectory(dir)),
+            None if command == "ls" => Ok(Line::List),
+            _ => bail!("Unknown command `{}`", command),
         },
 
-        Some(("dir", name)) => Some(Line::Directory(name)),
+        Some(("dir", name)) => Ok(Line::Directory(name)),
 
-        Some((size, _)) => Some(Line::File(
-            size.parse::<u32>().expect("Failed to parse file size"),
+        Some((size, name)) => Ok(Line::File(
+            name,
+            size.parse::<FileSize>()
+                .with_context(|| format!("Invalid file size `{}`", size))?,
         )),
 
-        _ => unreachable!("Invalid line"),
+        _ => bail!("Invalid line"),
     }
 }
 
-fn parse_input(input: &str) -> Tree<u32> {
-    let mut tree: Tree<u32> = Tree::new(vec![Node::new("/", 0)]);
+/// Applies a single transcript line to the tree.
+/// `is_listing` tracks whether we are currently reading the output of `ls`.
+fn apply_line(
+    file_system: &mut FileSystem,
+    line: &str,
+    is_listing: &mut bool,
+    mode: ParseMode,
+) -> Result<()> {
+    if line.starts_with('$') {
+        *is_listing = false;
+    }
 
-    for line in input.lines() {
-        match parse_line(line) {
-            Some(Line::ChangeDirectory(directory)) => tree.go_to_directory(directory),
-            Some(Line::Directory(dir_name)) => {
-                let new_node_index = tree.nodes.len();
-                let mut node = Node::new(dir_name, 0);
-                node.parent = Some(tree.current_index);
-                tree.insert(node);
-                tree.nodes[tree.current_index].children.push(new_node_index);
-            }
-            Some(Line::File(size)) => {
-                tree.add_file(tree.current_index, size);
-            }
-            None => {}
+    match parse_line(line)? {
+        Line::ChangeDirectory(directory) => match mode {
+            ParseMode::Strict => file_system.go_to_directory(directory)?,
+            ParseMode::Lenient => file_system.go_to_directory_or_create(directory)?,
+        },
+        Line::List => *is_listing = true,
+        Line::Directory(dir_name) => {
+            ensure!(*is_listing, "Directory outside of `ls` output");
+            ensure!(
+                file_system.child(dir_name).is_none(),
+                "Directory `{}` is already listed",
+                dir_name
+            );
+
+            file_system.insert_directory(dir_name);
+        }
+        Line::File(name, size) => {
+            ensure!(*is_listing, "File outside of `ls` output");
+            ensure!(
+                !file_system
+                    .directory(file_system.current_index)
+                    .files
+                    .iter()
+                    .any(|file| file.name == name),
+                "File `{}` is already listed",
+                name
+            );
+
+            file_system.add_file(file_system.current_index, name, size);
         }
     }
 
-    tree
+    Ok(())
 }
 
-fn part1(tree: &Tree<u32>) -> u32 {
+fn parse_input(input: &str, mode: ParseMode) -> Result<FileSystem> {
+    let mut file_system = FileSystem::new();
+    let mut is_listing = false;
+
+    for (index, line) in input.lines().enumerate() {
+        let result = apply_line(&mut file_system, line, &mut is_listing, mode);
+
+        if let (Err(error), ParseMode::Strict) = (result, mode) {
+            return Err(error.context(format!("Line {}: `{}`", index + 1, line)));
+        }
+    }
+
+    Ok(file_system)
+}
+
+fn part1(file_system: &FileSystem) -> FileSize {
     let max_directory_size = 100_000;
 
-    tree.nodes
-        .iter()
+    file_system
+        .directories()
         // Find all directories with a total size of at most 100'000
         .filter(|node| node.size <= max_directory_size)
         // Sum their total size
         .fold(0, |a, b| a + b.size)
 }
 
-fn part2(tree: &Tree<u32>) -> u32 {
-    let file_system_size = 70_000_000;
-    let required_unused_space = 30_000_000;
-    let root_size = tree.nodes[0].size;
+/// The disk the file system lives on, and how much of it must be unused.
+struct Disk {
+    size: FileSize,
+    required_unused_space: FileSize,
+}
+
+impl Default for Disk {
+    fn default() -> Self {
+        Self {
+            size: 70_000_000,
+            required_unused_space: 30_000_000,
+        }
+    }
+}
 
-    let deficit = required_unused_space - (file_system_size - root_size);
+/// Finds the size of the smallest directory that frees up enough space when deleted.
+/// Returns `None` if there already is enough unused space.
+fn part2(file_system: &FileSystem, disk: &Disk) -> Result<Option<FileSize>> {
+    let root_size = file_system.directory(file_system.tree.root()).size;
 
-    let mut possible_nodes = tree
-        .nodes
+    let unused_space = disk.size.saturating_sub(root_size);
+    let deficit = disk.required_unused_space.saturating_sub(unused_space);
+
+    if deficit == 0 {
+        return Ok(None);
+    }
+
+    file_system
+        .directories()
+        .map(|directory| directory.size)
+        .filter(|size| *size >= deficit)
+        .min()
+        .map(Some)
+        .ok_or_else(|| anyhow!("No directory frees up the required {} bytes", deficit))
+}
+
+/// Renders the file system in the puzzle's format, e.g. `- / (dir, size=48381165)`.
+/// Directories and files are listed alphabetically within each directory.
+fn render_tree(file_system: &FileSystem) -> String {
+    fn render_node(file_system: &FileSystem, index: NodeId, depth: usize, output: &mut String) {
+        let node = file_system.directory(index);
+        let indent = "  ".repeat(depth);
+        output.push_str(&format!(
+            "{}- {} (dir, size={})\n",
+            indent, node.label, node.size
+        ));
+
+        let mut entries = file_system
+            .tree
+            .children(index)
+            .iter()
+            .map(|child_index| {
+                (
+                    file_system.directory(*child_index).label.as_str(),
+                    Some(*child_index),
+                    None,
+                )
+            })
+            .chain(
+                node.files
+                    .iter()
+                    .map(|file| (file.name.as_str(), None, Some(file))),
+            )
+            .collect::<Vec<_>>();
+        entries.sort_by_key(|(name, _, _)| *name);
+
+        for entry in entries {
+            match entry {
+                (_, Some(child_index), _) => {
+                    render_node(file_system, child_index, depth + 1, output)
+                }
+                (name, _, Some(file)) => {
+                    output.push_str(&format!(
+                        "{}  - {} (file, size={})\n",
+                        indent, name, file.size
+                    ));
+                }
+                _ => unreachable!("Entry is either a directory or a file"),
+            }
+        }
+    }
+
+    let mut output = String::new();
+    render_node(file_system, file_system.tree.root(), 0, &mut output);
+    output
+}
+
+/// Formats a size like `du -h`, rounding up to one decimal below 10 and to whole units above.
+fn human_readable_size(size: FileSize) -> String {
+    const UNITS: [char; 4] = ['K', 'M', 'G', 'T'];
+
+    if size < 1024 {
+        return size.to_string();
+    }
+
+    let mut value = size as f64;
+    let mut unit = 0;
+
+    value /= 1024.0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if value < 10.0 {
+        format!("{:.1}{}", (value * 10.0).ceil() / 10.0, UNITS[unit])
+    } else {
+        format!("{}{}", value.ceil(), UNITS[unit])
+    }
+}
+
+/// Renders a `du -h`-style listing of every directory, largest first.
+fn render_disk_usage(file_system: &FileSystem) -> String {
+    let mut directories = file_system
+        .tree
         .iter()
-        .filter(|node| node.size >= deficit)
+        .map(|(index, directory)| (directory.size, file_system.path(index)))
         .collect::<Vec<_>>();
 
-    possible_nodes.sort_by(|a, b| b.size.cmp(&a.size));
+    directories.sort_by(|(a_size, a_path), (b_size, b_path)| {
+        b_size.cmp(a_size).then_with(|| a_path.cmp(b_path))
+    });
 
-    let node = possible_nodes.pop().expect("No node found");
-
-    node.size
+    directories
+        .iter()
+        .map(|(size, path)| format!("{}\t{}\n", human_readable_size(*size), path))
+        .collect()
 }
 
-pub fn solve() {
+pub fn solve(options: &Options) {
     let input = include_str!("../../input/day07.txt");
-    let file_system = parse_input(input);
+    let mode = if options.lenient {
+        ParseMode::Lenient
+    } else {
+        ParseMode::Strict
+    };
+    let file_system = parse_input(input, mode).expect("Failed to parse input");
+
+    if options.tree {
+        println!("{}", render_tree(&file_system));
+        println!("{}", render_disk_usage(&file_system));
+    }
 
     println!("Day 1 Part 1: {:?}", part1(&file_system));
-    println!("Day 1 Part 2: {:?}", part2(&file_system));
+    let disk = Disk {
+        size: options.disk_size,
+        required_unused_space: options.required_space,
+    };
+
+    match part2(&file_system, &disk).expect("Failed to find a directory to delete") {
+        Some(size) => println!("Day 1 Part 2: {:?}", size),
+        None => println!("Day 1 Part 2: No deletion needed"),
+    }
 }
 
 #[cfg(test)]
@@ -221,14 +434,100 @@ $ ls
 
     #[test]
     fn day07_part1() {
-        let file_system = parse_input(INPUT);
+        let file_system = parse_input(INPUT, ParseMode::Strict).unwrap();
         assert_eq!(part1(&file_system), 95437);
     }
 
     #[test]
     fn day07_part2() {
-        let file_system = parse_input(INPUT);
-        assert_eq!(part2(&file_system), 24933642);
+        let file_system = parse_input(INPUT, ParseMode::Strict).unwrap();
+        assert_eq!(
+            part2(&file_system, &Disk::default()).unwrap(),
+            Some(24933642)
+        );
     }
-}
 
+    #[test]
+    fn day07_render_tree() {
+        let file_system = parse_input(INPUT, ParseMode::Strict).unwrap();
+        let expected = "- / (dir, size=48381165)
+  - a (dir, size=94853)
+    - e (dir, size=584)
+      - i (file, size=584)
+    - f (file, size=29116)
+    - g (file, size=2557)
+    - h.lst (file, size=62596)
+  - b.txt (file, size=14848514)
+  - c.dat (file, size=8504156)
+  - d (dir, size=24933642)
+    - d.ext (file, size=5626152)
+    - d.log (file, size=8033020)
+    - j (file, size=4060174)
+    - k (file, size=7214296)
+";
+        assert_eq!(render_tree(&file_system), expected);
+    }
+
+    #[test]
+    fn day07_render_disk_usage() {
+        let file_system = parse_input(INPUT, ParseMode::Strict).unwrap();
+        let expected = "47M\t/
+24M\t/d
+93K\t/a
+584\t/a/e
+";
+        assert_eq!(render_disk_usage(&file_system), expected);
+    }
+
+    #[test]
+    fn day07_strict_reports_offending_line() {
+        let input = "$ cd /\n$ ls\ndir a\n$ cd b";
+        let error = parse_input(input, ParseMode::Strict).err().unwrap();
+        assert_eq!(error.to_string(), "Line 4: `$ cd b`");
+        assert_eq!(error.root_cause().to_string(), "Unknown directory `b`");
+    }
+
+    #[test]
+    fn day07_lenient_tolerates_malformed_transcript() {
+        let input = "$ cd /
+$ cd ..
+$ ls
+100 x
+$ ls
+100 x
+dir a
+$ pwd
+/
+$ cd b
+$ ls
+50 y";
+        let file_system = parse_input(input, ParseMode::Lenient).unwrap();
+        assert_eq!(file_system.directory(file_system.tree.root()).size, 150);
+        assert_eq!(file_system.path(file_system.current_index), "/b");
+        assert!(parse_input(input, ParseMode::Strict).is_err());
+    }
+
+    #[test]
+    fn day07_large_files() {
+        let input = "$ cd /\n$ ls\n5000000000 a\ndir b\n$ cd b\n$ ls\n6000000000 c";
+        let file_system = parse_input(input, ParseMode::Strict).unwrap();
+        let disk = Disk {
+            size: 16_000_000_000,
+            required_unused_space: 8_000_000_000,
+        };
+
+        assert_eq!(part1(&file_system), 0);
+        assert_eq!(part2(&file_system, &disk).unwrap(), Some(6_000_000_000));
+    }
+
+    #[test]
+    fn day07_no_deletion_needed() {
+        let file_system = parse_input(INPUT, ParseMode::Strict).unwrap();
+        let disk = Disk {
+            size: 100_000_000,
+            required_unused_space: 30_000_000,
+        };
+
+        assert_eq!(part2(&file_system, &disk).unwrap(), None);
+    }
+}
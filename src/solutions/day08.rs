@@ -1,4 +1,9 @@
-use crate::utils::{date::Date, io::read_input};
+use std::{fs::write, path::Path};
+
+use anyhow::{anyhow, ensure, Result};
+use itertools::Itertools;
+
+use crate::utils::{date::Date, io::read_input, options::Options};
 
 struct Visible {
     left: bool,
@@ -7,28 +12,68 @@ struct Visible {
     bottom: bool,
 }
 
+/// A rectangular grid of tree heights, indexed as `grid[row][col]`.
+/// The constructor guarantees that every row has exactly `width` trees.
 #[derive(Debug)]
 struct Forest {
-    rows: usize,
-    cols: usize,
+    /// The number of columns, i.e. trees per row.
+    width: usize,
+    /// The number of rows.
+    height: usize,
     grid: Vec<Vec<u32>>,
 }
 
 impl Forest {
-    fn new(input: &[String]) -> Self {
-        let grid = input
+    /// Parses one row of trees per line, ignoring blank lines.
+    /// Fails on non-digit characters and on rows of differing lengths.
+    fn new(input: &[String]) -> Result<Self> {
+        // Rows keep the index of their line, so errors point at the input line
+        // even when blank lines were skipped.
+        let rows = input
             .iter()
-            .map(|line| {
-                line.chars()
-                    .map(|character| character.to_digit(10).expect("Invalid character"))
-                    .collect::<Vec<_>>()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(line_index, line)| {
+                let row = line
+                    .trim_end()
+                    .chars()
+                    .enumerate()
+                    .map(|(col, character)| {
+                        character.to_digit(10).ok_or_else(|| {
+                            anyhow!(
+                                "Invalid tree `{}` at line {}, column {}",
+                                character,
+                                line_index + 1,
+                                col + 1
+                            )
+                        })
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                Ok((line_index, row))
             })
-            .collect::<Vec<_>>();
+            .collect::<Result<Vec<_>>>()?;
 
-        let rows = grid[0].len();
-        let cols = grid.len();
+        let width = rows.first().map_or(0, |(_, row)| row.len());
 
-        Self { rows, cols, grid }
+        ensure!(width > 0, "The forest has no trees");
+
+        if let Some((line_index, row)) = rows.iter().find(|(_, row)| row.len() != width) {
+            return Err(anyhow!(
+                "Line {} has {} trees, but the first row has {}",
+                line_index + 1,
+                row.len(),
+                width
+            ));
+        }
+
+        let grid = rows.into_iter().map(|(_, row)| row).collect::<Vec<_>>();
+        let height = grid.len();
+
+        Ok(Self {
+            width,
+            height,
+            grid,
+        })
     }
 
     /// Determines if the given tree in the forest is visible.
@@ -58,7 +103,10 @@ impl Forest {
     /// Determines if a value is on the edge of the grid.
     /// A value is on the edge if it is on the first or last row or column.
     fn is_on_edge(&self, row_index: usize, col_index: usize) -> bool {
-        row_index == 0 || row_index == self.cols - 1 || col_index == 0 || col_index == self.rows - 1
+        row_index == 0
+            || row_index == self.height - 1
+            || col_index == 0
+            || col_index == self.width - 1
     }
 
     fn scenic_score(&self, row_index: usize, col_index: usize) -> usize {
@@ -78,7 +126,7 @@ impl Forest {
 
         // Right
         run = 0;
-        for i in col_index + 1..self.rows {
+        for i in col_index + 1..self.width {
             run += 1;
             if self.grid[row_index][i] >= current_value {
                 break;
@@ -98,7 +146,7 @@ impl Forest {
 
         // Bottom
         run = 0;
-        for i in row_index + 1..self.cols {
+        for i in row_index + 1..self.height {
             run += 1;
             if self.grid[i][col_index] >= current_value {
                 break;
@@ -110,47 +158,265 @@ impl Forest {
     }
 }
 
-fn part1(input: &[String]) -> usize {
-    let forest = Forest::new(input);
+/// Visibility and scenic score of every tree in a forest.
+#[derive(Debug, PartialEq)]
+struct ForestAnalysis {
+    visible: Vec<Vec<bool>>,
+    scenic_scores: Vec<Vec<usize>>,
+}
 
-    forest
-        .grid
+impl Forest {
+    /// Computes visibility and scenic scores tree by tree,
+    /// using [`Forest::is_visible`] and [`Forest::scenic_score`].
+    fn analyse_naive(&self) -> ForestAnalysis {
+        let (visible, scenic_scores) = (0..self.height)
+            .map(|row| {
+                (0..self.width)
+                    .map(|col| {
+                        (
+                            self.is_visible(row, col) || self.is_on_edge(row, col),
+                            self.scenic_score(row, col),
+                        )
+                    })
+                    .unzip()
+            })
+            .unzip();
+
+        ForestAnalysis {
+            visible,
+            scenic_scores,
+        }
+    }
+
+    /// Computes visibility and scenic scores for the whole grid in linear time,
+    /// as opposed to [`Forest::is_visible`] and [`Forest::scenic_score`],
+    /// which scan all four directions for every tree.
+    ///
+    /// Every row and column is swept once in each direction. A tree is visible
+    /// from that direction if it is taller than the tallest tree seen so far,
+    /// and a monotonic stack of the trees that can still block the view
+    /// gives the viewing distance.
+    fn analyse(&self) -> ForestAnalysis {
+        let (height, width) = (self.height, self.width);
+
+        let mut analysis = ForestAnalysis {
+            visible: vec![vec![false; width]; height],
+            scenic_scores: vec![vec![1; width]; height],
+        };
+
+        let mut lines: Vec<Vec<(usize, usize)>> = Vec::new();
+        for row in 0..height {
+            let line = (0..width).map(|col| (row, col)).collect::<Vec<_>>();
+            lines.push(line.iter().rev().copied().collect());
+            lines.push(line);
+        }
+        for col in 0..width {
+            let line = (0..height).map(|row| (row, col)).collect::<Vec<_>>();
+            lines.push(line.iter().rev().copied().collect());
+            lines.push(line);
+        }
+
+        for line in lines {
+            self.sweep(&line, &mut analysis);
+        }
+
+        analysis
+    }
+
+    /// Looks back along a line of trees from each tree in turn.
+    fn sweep(&self, line: &[(usize, usize)], analysis: &mut ForestAnalysis) {
+        let mut tallest: Option<u32> = None;
+        // Indices into `line` of trees that may block the view, strictly decreasing in height.
+        let mut blockers: Vec<usize> = Vec::new();
+
+        for (index, &(row, col)) in line.iter().enumerate() {
+            let tree = self.grid[row][col];
+
+            if tallest.is_none_or(|tallest| tree > tallest) {
+                analysis.visible[row][col] = true;
+                tallest = Some(tree);
+            }
+
+            while let Some(&blocker) = blockers.last() {
+                let (blocker_row, blocker_col) = line[blocker];
+                if self.grid[blocker_row][blocker_col] >= tree {
+                    break;
+                }
+                blockers.pop();
+            }
+
+            let viewing_distance = match blockers.last() {
+                Some(&blocker) => index - blocker,
+                None => index,
+            };
+            analysis.scenic_scores[row][col] *= viewing_distance;
+
+            blockers.push(index);
+        }
+    }
+}
+
+fn part1(analysis: &ForestAnalysis) -> usize {
+    analysis
+        .visible
+        .iter()
+        .flatten()
+        .filter(|&&visible| visible)
+        .count()
+}
+
+fn part2(analysis: &ForestAnalysis) -> usize {
+    *analysis
+        .scenic_scores
+        .iter()
+        .flatten()
+        .max()
+        .expect("No max score found")
+}
+
+/// Finds the tree with the highest scenic score, as 0-based `(row, col, score)`.
+/// Ties are broken by the first tree in reading order.
+fn best_tree(analysis: &ForestAnalysis) -> Option<(usize, usize, usize)> {
+    analysis
+        .scenic_scores
         .iter()
         .enumerate()
-        .map(|(i, row)| {
-            row.iter()
+        .flat_map(|(row, scores)| {
+            scores
+                .iter()
                 .enumerate()
-                .filter(|(j, _)| forest.is_visible(i, *j) || forest.is_on_edge(i, *j))
-                .count()
+                .map(move |(col, &score)| (row, col, score))
         })
-        .sum::<usize>()
+        .rev()
+        .max_by_key(|&(_, _, score)| score)
 }
 
-fn part2(input: &[String]) -> usize {
-    let forest = Forest::new(input);
+const RESET: &str = "\x1B[0m";
+
+/// Renders the tree heights, coloured green if visible from outside the grid and red otherwise.
+fn render_visibility(forest: &Forest, analysis: &ForestAnalysis) -> String {
+    const VISIBLE: &str = "\x1B[1;32m";
+    const HIDDEN: &str = "\x1B[2;31m";
 
-    let scores = forest
+    forest
         .grid
         .iter()
+        .zip(&analysis.visible)
+        .map(|(trees, visible)| {
+            trees
+                .iter()
+                .zip(visible)
+                .map(|(tree, &visible)| {
+                    let colour = if visible { VISIBLE } else { HIDDEN };
+                    format!("{}{}{}", colour, tree, RESET)
+                })
+                .collect::<String>()
+                + "\n"
+        })
+        .collect()
+}
+
+/// Renders the scenic scores as a heatmap on a grayscale background,
+/// with the best tree marked by `<>`.
+fn render_scenic_scores(analysis: &ForestAnalysis) -> String {
+    // The 24 shades from black to white in the 256-colour palette.
+    const FIRST_SHADE: usize = 232;
+    const SHADES: usize = 24;
+
+    let max_score = analysis.scenic_scores.iter().flatten().max().copied();
+    let best = best_tree(analysis);
+
+    analysis
+        .scenic_scores
+        .iter()
         .enumerate()
-        .map(|(i, row)| {
-            row.iter()
+        .map(|(row, scores)| {
+            scores
+                .iter()
                 .enumerate()
-                .map(|(j, _)| forest.scenic_score(i, j))
-                .collect::<Vec<_>>()
+                .map(|(col, &score)| {
+                    let shade = match max_score {
+                        Some(max_score) if max_score > 0 => score * (SHADES - 1) / max_score,
+                        _ => 0,
+                    };
+                    let marker = match best {
+                        Some((best_row, best_col, _)) if (best_row, best_col) == (row, col) => "<>",
+                        _ => "  ",
+                    };
+                    format!("\x1B[48;5;{}m{}{}", FIRST_SHADE + shade, marker, RESET)
+                })
+                .collect::<String>()
+                + "\n"
+        })
+        .collect()
+}
+
+/// Encodes a grid of values as a plain (ASCII) PGM image.
+/// Values are scaled down to fit in 255 shades of grey if needed.
+fn to_pgm(values: &[Vec<usize>]) -> String {
+    let height = values.len();
+    let width = values.first().map_or(0, Vec::len);
+    let max_value = values.iter().flatten().max().copied().unwrap_or(0).max(1);
+    let max_grey = max_value.min(255);
+
+    let pixels = values
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|value| (value * max_grey / max_value).to_string())
+                .join(" ")
         })
+        .join("\n");
+
+    format!("P2\n{} {}\n{}\n{}\n", width, height, max_grey, pixels)
+}
+
+/// Writes the visibility mask and the scenic scores as PGM images to a directory.
+fn write_pgm_images(analysis: &ForestAnalysis, directory: &Path) -> Result<()> {
+    let visible = analysis
+        .visible
+        .iter()
+        .map(|row| row.iter().map(|&visible| visible as usize).collect())
         .collect::<Vec<_>>();
 
-    let max_score = scores.iter().flatten().max().expect("No max score found");
+    write(directory.join("day08-visibility.pgm"), to_pgm(&visible))?;
+    write(
+        directory.join("day08-scenic-scores.pgm"),
+        to_pgm(&analysis.scenic_scores),
+    )?;
 
-    *max_score
+    Ok(())
 }
 
-pub fn solve() {
+pub fn solve(options: &Options) {
     let input: Vec<String> = read_input(Date::Day08).expect("failed to read input");
+    let forest = Forest::new(&input).expect("Invalid forest");
+    let analysis = if options.naive {
+        forest.analyse_naive()
+    } else {
+        forest.analyse()
+    };
+
+    if options.render {
+        println!("{}", render_visibility(&forest, &analysis));
+        println!("{}", render_scenic_scores(&analysis));
 
-    println!("Day 1 Part 1: {:?}", part1(&input));
-    println!("Day 1 Part 2: {:?}", part2(&input));
+        if let Some((row, col, score)) = best_tree(&analysis) {
+            println!(
+                "Best tree: row {}, column {} (scenic score {})",
+                row + 1,
+                col + 1,
+                score
+            );
+        }
+    }
+
+    if let Some(directory) = &options.pgm {
+        write_pgm_images(&analysis, directory).expect("Failed to write images");
+    }
+
+    println!("Day 1 Part 1: {:?}", part1(&analysis));
+    println!("Day 1 Part 2: {:?}", part2(&analysis));
 }
 
 #[cfg(test)]
@@ -174,19 +440,116 @@ mod tests {
     #[test]
     fn day08_part1() {
         let actual = get_input();
-        assert_eq!(part1(&actual), 21);
+        let forest = Forest::new(&actual).unwrap();
+        assert_eq!(part1(&forest.analyse()), 21);
+        assert_eq!(part1(&forest.analyse_naive()), 21);
     }
 
     #[test]
     fn day08_part2_demo() {
         let actual = get_input();
-        let forest = Forest::new(&actual);
+        let forest = Forest::new(&actual).unwrap();
         assert_eq!(forest.scenic_score(1, 2), 4);
     }
 
     #[test]
     fn day08_part2() {
         let actual = get_input();
-        assert_eq!(part2(&actual), 8);
+        let forest = Forest::new(&actual).unwrap();
+        assert_eq!(part2(&forest.analyse()), 8);
+        assert_eq!(part2(&forest.analyse_naive()), 8);
+    }
+
+    #[test]
+    fn day08_best_tree() {
+        let forest = Forest::new(&get_input()).unwrap();
+        assert_eq!(best_tree(&forest.analyse()), Some((3, 2, 8)));
+    }
+
+    #[test]
+    fn day08_pgm() {
+        let forest = Forest::new(&get_input()).unwrap();
+        let analysis = forest.analyse();
+
+        assert_eq!(
+            to_pgm(&analysis.scenic_scores),
+            "P2
+5 5
+8
+0 0 0 0 0
+0 1 4 1 0
+0 6 1 2 0
+0 1 8 3 0
+0 0 0 0 0
+"
+        );
+    }
+
+    /// Generates a forest of pseudo-random trees from a seed.
+    fn generate_forest(width: usize, height: usize, seed: u64) -> Vec<String> {
+        let mut state = seed;
+        let mut next_digit = move || {
+            // Linear congruential generator, see Knuth's MMIX.
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            char::from_digit(((state >> 33) % 10) as u32, 10).unwrap()
+        };
+
+        (0..height)
+            .map(|_| (0..width).map(|_| next_digit()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn day08_linear_matches_naive() {
+        let sizes = [
+            (1, 1),
+            (2, 2),
+            (7, 7),
+            (60, 60),
+            (1, 5),
+            (5, 1),
+            (3, 8),
+            (40, 13),
+        ];
+
+        for (seed, (width, height)) in sizes.into_iter().enumerate() {
+            let forest = Forest::new(&generate_forest(width, height, seed as u64)).unwrap();
+            assert_eq!(forest.analyse(), forest.analyse_naive());
+        }
+    }
+
+    #[test]
+    fn day08_non_square_forest() {
+        let input = ["30373", "25512", "65332"].map(String::from);
+        let forest = Forest::new(&input).unwrap();
+        assert_eq!((forest.width, forest.height), (5, 3));
+
+        let analysis = forest.analyse();
+        assert_eq!(part1(&analysis), 14);
+        assert_eq!(part2(&analysis), 2);
+        assert_eq!(analysis, forest.analyse_naive());
+
+        let input = ["303", "255", "653", "335", "353"].map(String::from);
+        let forest = Forest::new(&input).unwrap();
+        assert_eq!((forest.width, forest.height), (3, 5));
+        assert_eq!(part1(&forest.analyse()), 14);
+        assert_eq!(forest.analyse(), forest.analyse_naive());
+    }
+
+    #[test]
+    fn day08_ragged_forest() {
+        // Both errors count blank lines, so they point at the same input lines.
+        let input = ["303", "", "25", "653"].map(String::from);
+        let error = Forest::new(&input).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Line 3 has 2 trees, but the first row has 3"
+        );
+
+        let input = ["303", "", "2x5"].map(String::from);
+        let error = Forest::new(&input).unwrap_err();
+        assert_eq!(error.to_string(), "Invalid tree `x` at line 3, column 2");
     }
 }
@@ -1,181 +1,625 @@
-use std::{collections::HashSet, str::FromStr};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Display,
+    fs::write,
+    ops::{Add, Mul, Sub},
+    str::FromStr,
+};
 
-use crate::utils::{date::Date, io::read_input};
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use itertools::Itertools;
 
-enum Movement {
-    Up,
-    Down,
-    Left,
-    Right,
-}
+use crate::utils::{date::Date, io::read_input, options::Options};
 
 struct Instruction {
-    movement: Movement,
+    /// The vector the head moves by in each step, which is never zero.
+    direction: Coordinate,
     times: usize,
 }
 
+impl Display for Instruction {
+    /// Writes the direction as letters if possible, e.g. `UR 3`,
+    /// and as a vector otherwise, e.g. `2,-1,0 3`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Coordinate { x, y, z } = self.direction;
+
+        if [x, y, z].iter().all(|component| component.abs() <= 1) {
+            let letters = [(y, 'U', 'D'), (x, 'R', 'L'), (z, 'F', 'B')]
+                .into_iter()
+                .filter_map(|(component, positive, negative)| match component {
+                    1 => Some(positive),
+                    -1 => Some(negative),
+                    _ => None,
+                })
+                .collect::<String>();
+
+            write!(f, "{} {}", letters, self.times)
+        } else {
+            write!(f, "{},{},{} {}", x, y, z, self.times)
+        }
+    }
+}
+
 impl FromStr for Instruction {
     type Err = anyhow::Error;
 
+    /// Parses an instruction like `R 4`, a diagonal like `UR 3`, a 3D move like `UF 2`
+    /// (`F`/`B` move forward and backward along `z`) or an arbitrary vector like `2,-1 5`.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (movement, distance) = s.split_once(' ').expect("Invalid input");
-
-        let movement = match movement {
-            "R" => Movement::Right,
-            "L" => Movement::Left,
-            "U" => Movement::Up,
-            "D" => Movement::Down,
-            _ => unreachable!("Invalid input"),
+        let (letters, distance) = s
+            .split_once(' ')
+            .ok_or_else(|| anyhow!("Invalid instruction `{}`", s))?;
+
+        let direction = if letters.contains(',') {
+            let components = letters
+                .split(',')
+                .map(|component| component.parse::<isize>())
+                .collect::<Result<Vec<_>, _>>()?;
+
+            match components[..] {
+                [x, y] => Coordinate { x, y, z: 0 },
+                [x, y, z] => Coordinate { x, y, z },
+                _ => bail!("Invalid direction `{}`", letters),
+            }
+        } else {
+            letters
+                .chars()
+                .map(|letter| match letter {
+                    'R' => Ok(Coordinate { x: 1, y: 0, z: 0 }),
+                    'L' => Ok(Coordinate { x: -1, y: 0, z: 0 }),
+                    'U' => Ok(Coordinate { x: 0, y: 1, z: 0 }),
+                    'D' => Ok(Coordinate { x: 0, y: -1, z: 0 }),
+                    'F' => Ok(Coordinate { x: 0, y: 0, z: 1 }),
+                    'B' => Ok(Coordinate { x: 0, y: 0, z: -1 }),
+                    _ => Err(anyhow!("Invalid direction `{}`", letter)),
+                })
+                .fold_ok(Coordinate::default(), |sum, unit| sum + unit)?
         };
 
-        let distance = distance.parse::<usize>().expect("Invalid value");
+        ensure!(
+            direction != Coordinate::default(),
+            "Direction `{}` does not move the head",
+            letters
+        );
+
+        let distance = distance.parse::<usize>()?;
 
         Ok(Self {
-            movement,
+            direction,
             times: distance,
         })
     }
 }
 
-#[derive(Eq, PartialEq, Clone, Copy, Hash)]
+#[derive(Eq, PartialEq, Clone, Copy, Hash, Default, Debug)]
 struct Coordinate {
     x: isize,
     y: isize,
+    z: isize,
+}
+
+impl Add for Coordinate {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Coordinate {
+            x: self.x + other.x,
+            y: self.y + other.y,
+            z: self.z + other.z,
+        }
+    }
+}
+
+impl Sub for Coordinate {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Coordinate {
+            x: self.x - other.x,
+            y: self.y - other.y,
+            z: self.z - other.z,
+        }
+    }
+}
+
+impl Mul<isize> for Coordinate {
+    type Output = Self;
+
+    fn mul(self, factor: isize) -> Self {
+        Coordinate {
+            x: self.x * factor,
+            y: self.y * factor,
+            z: self.z * factor,
+        }
+    }
 }
 
 impl Coordinate {
+    fn components(&self) -> [isize; 3] {
+        [self.x, self.y, self.z]
+    }
+
+    /// Returns `true` if the coordinate moves at most one cell along each axis.
+    fn is_unit(&self) -> bool {
+        self.components()
+            .iter()
+            .all(|component| component.abs() <= 1)
+    }
+
+    fn signum(&self) -> Self {
+        Coordinate {
+            x: self.x.signum(),
+            y: self.y.signum(),
+            z: self.z.signum(),
+        }
+    }
+
     fn is_adjacent_or_overlaps(&self, other: &Self) -> bool {
-        (self.x - other.x).abs() <= 1 && (self.y - other.y).abs() <= 1
+        let difference = *self - *other;
+
+        [difference.x, difference.y, difference.z]
+            .iter()
+            .all(|component| component.abs() <= 1)
     }
 }
 
-#[derive(Clone)]
-struct Knot {
-    position: Coordinate,
-    visited: HashSet<Coordinate>,
+/// A line through the grid, identified by its direction and the point on it
+/// where the parameter `t` is zero. Cells on the line are `base + direction * t`.
+type Line = (Coordinate, Coordinate);
+
+/// Finds the line through `point` along `direction`, and the parameter of `point` on it.
+/// Directions are normalised so that their first non-zero component is positive,
+/// which means that both directions along a line give the same key.
+fn line_through(point: &Coordinate, direction: &Coordinate) -> (Line, isize) {
+    let (axis, &sign) = direction
+        .components()
+        .iter()
+        .enumerate()
+        .find(|(_, component)| **component != 0)
+        .expect("A line needs a direction");
+
+    let direction = *direction * sign;
+    let t = point.components()[axis] * direction.components()[axis];
+
+    ((direction, *point - direction * t), t)
 }
 
-impl Knot {
-    fn new(position: Coordinate) -> Self {
-        let visited = HashSet::from([position]);
-        Self { position, visited }
+/// Finds the parameters `(s, t)` where two lines cross at a cell, if they do.
+fn intersection(
+    (first_direction, first_base): &Line,
+    (second_direction, second_base): &Line,
+) -> Option<(isize, isize)> {
+    let (d1, d2) = (first_direction.components(), second_direction.components());
+    let offset = (*second_base - *first_base).components();
+
+    // Solve `first_base + d1 * s = second_base + d2 * t` on two axes, then check the third.
+    for (i, j) in [(0, 1), (0, 2), (1, 2)] {
+        let determinant = d2[i] * d1[j] - d1[i] * d2[j];
+
+        if determinant == 0 {
+            continue;
+        }
+
+        let s = d2[i] * offset[j] - offset[i] * d2[j];
+        let t = d1[i] * offset[j] - offset[i] * d1[j];
+
+        if s % determinant != 0 || t % determinant != 0 {
+            return None;
+        }
+
+        let (s, t) = (s / determinant, t / determinant);
+        let crosses = *first_base + *first_direction * s == *second_base + *second_direction * t;
+
+        return crosses.then_some((s, t));
     }
 
-    fn move_to(&mut self, position: Coordinate) {
-        self.position = position;
-        self.visited.insert(position);
+    // The lines are parallel, and distinct lines never share a cell.
+    None
+}
+
+/// Merges overlapping and adjacent intervals in place.
+fn merge_intervals(intervals: &mut Vec<(isize, isize)>) {
+    intervals.sort_unstable();
+
+    let mut merged: Vec<(isize, isize)> = Vec::with_capacity(intervals.len());
+
+    for &(start, end) in intervals.iter() {
+        match merged.last_mut() {
+            Some((_, last_end)) if start <= *last_end + 1 => *last_end = (*last_end).max(end),
+            _ => merged.push((start, end)),
+        }
     }
+
+    *intervals = merged;
 }
 
-struct KnottedRope<const KNOTS_COUNT: usize> {
-    knots: Vec<Knot>,
+/// Checks whether `t` lies in one of the sorted, merged intervals.
+fn intervals_contain(intervals: &[(isize, isize)], t: isize) -> bool {
+    let index = intervals.partition_point(|&(start, _)| start <= t);
+    index > 0 && intervals[index - 1].1 >= t
 }
 
-impl<const KNOTS_COUNT: usize> KnottedRope<KNOTS_COUNT> {
-    fn new() -> Self {
-        let start = Coordinate { x: 0, y: 0 };
-        let knots = vec![Knot::new(start); KNOTS_COUNT];
-        Self { knots }
+/// The cells visited by a knot. Long straight moves are stored as segments
+/// on a line instead of one entry per cell, so they take constant space.
+#[derive(Default)]
+struct VisitedCells {
+    cells: HashSet<Coordinate>,
+    segments: HashMap<Line, Vec<(isize, isize)>>,
+}
+
+impl VisitedCells {
+    fn insert(&mut self, cell: Coordinate) {
+        self.cells.insert(cell);
     }
 
-    fn tail(&self) -> &Knot {
-        self.knots.last().expect("No knots")
+    /// Records the cells from `start` up to and including `start + direction * length`.
+    fn insert_segment(&mut self, start: Coordinate, direction: Coordinate, length: isize) {
+        let end = start + direction * length;
+        let (line, start) = line_through(&start, &direction);
+        let (_, end) = line_through(&end, &direction);
+
+        self.segments
+            .entry(line)
+            .or_default()
+            .push((start.min(end), start.max(end)));
     }
 
-    fn update(&mut self, instruction: &Instruction) {
-        let mut iterations = instruction.times;
-
-        while iterations > 0 {
-            let mut previous_position: Option<Coordinate> = None;
-
-            for knot in self.knots.iter_mut() {
-                let Coordinate { x, y } = knot.position;
-
-                match previous_position {
-                    Some(previous_position) => {
-                        if !knot.position.is_adjacent_or_overlaps(&previous_position) {
-                            let Coordinate {
-                                x: previous_x,
-                                y: previous_y,
-                            } = previous_position;
-
-                            if x == previous_x {
-                                if y < previous_y {
-                                    knot.move_to(Coordinate { x, y: y + 1 });
-                                } else {
-                                    knot.move_to(Coordinate { x, y: y - 1 });
-                                }
-                            } else if y == previous_y {
-                                if x < previous_x {
-                                    knot.move_to(Coordinate { x: x + 1, y });
-                                } else {
-                                    knot.move_to(Coordinate { x: x - 1, y });
-                                }
-                            } else if x < previous_x {
-                                if y < previous_y {
-                                    knot.move_to(Coordinate { x: x + 1, y: y + 1 });
-                                } else {
-                                    knot.move_to(Coordinate { x: x + 1, y: y - 1 });
-                                }
-                            } else if y < previous_y {
-                                knot.move_to(Coordinate { x: x - 1, y: y + 1 });
-                            } else {
-                                knot.move_to(Coordinate { x: x - 1, y: y - 1 });
-                            }
-                        }
-                    }
-                    None => {
-                        match instruction.movement {
-                            Movement::Up => {
-                                knot.move_to(Coordinate { x, y: y + 1 });
-                            }
-                            Movement::Down => {
-                                knot.move_to(Coordinate { x, y: y - 1 });
-                            }
-                            Movement::Left => {
-                                knot.move_to(Coordinate { x: x - 1, y });
-                            }
-                            Movement::Right => {
-                                knot.move_to(Coordinate { x: x + 1, y });
-                            }
-                        };
+    /// Counts the distinct cells visited, without expanding the segments.
+    fn len(&self) -> usize {
+        let mut lines = self.segments.clone();
+        lines.values_mut().for_each(merge_intervals);
+        let lines = lines.into_iter().collect::<Vec<_>>();
+
+        let on_segments = lines
+            .iter()
+            .flat_map(|(_, intervals)| intervals)
+            .map(|(start, end)| (end - start + 1) as usize)
+            .sum::<usize>();
+
+        // Cells where lines cross are counted once per line, so collect the lines through each.
+        let mut crossings: HashMap<Coordinate, HashSet<usize>> = HashMap::new();
+        for (i, (first, first_intervals)) in lines.iter().enumerate() {
+            for (j, (second, second_intervals)) in lines.iter().enumerate().skip(i + 1) {
+                if let Some((s, t)) = intersection(first, second) {
+                    if intervals_contain(first_intervals, s)
+                        && intervals_contain(second_intervals, t)
+                    {
+                        let cell = first.1 + first.0 * s;
+                        crossings.entry(cell).or_default().extend([i, j]);
                     }
                 }
+            }
+        }
+        let counted_twice = crossings
+            .values()
+            .map(|lines| lines.len() - 1)
+            .sum::<usize>();
+
+        let merged = lines.into_iter().collect::<HashMap<_, _>>();
+        let single_cells = self
+            .cells
+            .iter()
+            .filter(|cell| {
+                !DIRECTIONS.iter().any(|direction| {
+                    let (line, t) = line_through(cell, direction);
+                    merged
+                        .get(&line)
+                        .is_some_and(|intervals| intervals_contain(intervals, t))
+                })
+            })
+            .count();
+
+        on_segments - counted_twice + single_cells
+    }
+
+    /// Iterates over every visited cell, expanding the segments.
+    fn iter(&self) -> impl Iterator<Item = Coordinate> + '_ {
+        let on_segments = self
+            .segments
+            .iter()
+            .flat_map(|((direction, base), intervals)| {
+                intervals.iter().flat_map(move |&(start, end)| {
+                    (start..=end).map(move |t| *base + *direction * t)
+                })
+            });
+
+        self.cells.iter().copied().chain(on_segments)
+    }
+}
+
+/// All directions a line can have, normalised as in [`line_through`].
+const DIRECTIONS: [Coordinate; 13] = {
+    let mut directions = [Coordinate { x: 0, y: 0, z: 0 }; 13];
+    let mut index = 0;
+    let mut code = 0;
+
+    // Enumerate every direction in {-1, 0, 1}³ and keep those pointing "forwards".
+    while code < 27 {
+        let (x, y, z) = (code / 9 - 1, code / 3 % 3 - 1, code % 3 - 1);
+        let first = if x != 0 {
+            x
+        } else if y != 0 {
+            y
+        } else {
+            z
+        };
+
+        if first > 0 {
+            directions[index] = Coordinate { x, y, z };
+            index += 1;
+        }
 
-                previous_position = Some(knot.position);
+        code += 1;
+    }
+
+    directions
+};
+
+/// A rope of knots, where each knot follows the one in front of it.
+/// Cells visited are only recorded for the knots that are tracked.
+struct KnottedRope {
+    knots: Vec<Coordinate>,
+    visited: BTreeMap<usize, VisitedCells>,
+}
+
+impl KnottedRope {
+    /// Creates a rope of `knots_count` knots at the origin,
+    /// recording the cells visited by the knots at the `tracked` indices.
+    fn new(knots_count: usize, tracked: &[usize]) -> Result<Self> {
+        ensure!(knots_count > 0, "A rope needs at least one knot");
+
+        let start = Coordinate::default();
+        let knots = vec![start; knots_count];
+
+        let visited = tracked
+            .iter()
+            .map(|&index| {
+                ensure!(
+                    index < knots_count,
+                    "Knot {} does not exist in a rope of {} knots",
+                    index,
+                    knots_count
+                );
+                let mut visited = VisitedCells::default();
+                visited.insert(start);
+                Ok((index, visited))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self { knots, visited })
+    }
+
+    fn move_knot(&mut self, index: usize, position: Coordinate) {
+        self.knots[index] = position;
+
+        if let Some(visited) = self.visited.get_mut(&index) {
+            visited.insert(position);
+        }
+    }
+
+    /// Gets the number of cells visited by each tracked knot, ordered by knot index.
+    fn visited_counts(&self) -> Vec<(usize, usize)> {
+        self.visited
+            .iter()
+            .map(|(&index, visited)| (index, visited.len()))
+            .collect()
+    }
+
+    /// Moves the head as many steps as the instruction says.
+    /// Steps are simulated one by one until a step moves every knot by exactly `direction`.
+    /// Knots follow based only on where they are relative to each other, which that step
+    /// left unchanged, so every later step is the same translation and the whole rope
+    /// just slides along in a single bulk move.
+    fn update(&mut self, instruction: &Instruction) {
+        let direction = instruction.direction;
+
+        for done in 1..=instruction.times {
+            let before = self.knots.clone();
+            self.step(&direction);
+
+            let is_translation = self
+                .knots
+                .iter()
+                .zip(&before)
+                .all(|(&knot, &previous)| knot == previous + direction);
+
+            if direction.is_unit() && is_translation && done < instruction.times {
+                self.slide(&direction, (instruction.times - done) as isize);
+                return;
             }
+        }
+    }
 
-            iterations -= 1;
+    /// Moves every knot `length` steps along `direction`, keeping the shape of the rope.
+    fn slide(&mut self, direction: &Coordinate, length: isize) {
+        for (index, knot) in self.knots.iter_mut().enumerate() {
+            if let Some(visited) = self.visited.get_mut(&index) {
+                visited.insert_segment(*knot, *direction, length);
+            }
+
+            *knot = *knot + *direction * length;
+        }
+    }
+
+    /// Moves the head one step, and lets the other knots follow.
+    /// A knot that is no longer touching the knot in front of it moves one cell
+    /// towards it along every axis where they differ, until they touch again.
+    /// Knots only need more than one such move when the head moves by more than one cell.
+    fn step(&mut self, direction: &Coordinate) {
+        self.move_knot(0, self.knots[0] + *direction);
+
+        for index in 1..self.knots.len() {
+            let previous = self.knots[index - 1];
+
+            while !self.knots[index].is_adjacent_or_overlaps(&previous) {
+                let knot = self.knots[index];
+                self.move_knot(index, knot + (previous - knot).signum());
+            }
         }
     }
 }
 
-fn visited_by_tail<const KNOTS_COUNT: usize>(input: &[String]) -> usize {
-    let mut rope = KnottedRope::<KNOTS_COUNT>::new();
+impl Display for KnottedRope {
+    /// Draws the rope like the puzzle's illustrations, with `y` pointing up.
+    /// Ropes in 3D are projected onto the `x`-`y` plane.
+    /// The head is `H`, the other knots are numbered (or `T` for the tail of a two-knot rope),
+    /// the start is `s` and cells visited by tracked knots are `#`.
+    /// The drawing is just large enough to fit all of them.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let project = |cell: &Coordinate| Coordinate { z: 0, ..*cell };
+        let start = Coordinate::default();
+        let knots = self.knots.iter().map(project).collect::<Vec<_>>();
+        let visited = self
+            .visited
+            .values()
+            .flat_map(VisitedCells::iter)
+            .map(|cell| project(&cell))
+            .collect::<HashSet<_>>();
+
+        let cells = knots.iter().chain([&start]).chain(visited.iter());
+        let (min_x, max_x) = cells
+            .clone()
+            .map(|cell| cell.x)
+            .minmax()
+            .into_option()
+            .expect("The start is always drawn");
+        let (min_y, max_y) = cells
+            .map(|cell| cell.y)
+            .minmax()
+            .into_option()
+            .expect("The start is always drawn");
+
+        for y in (min_y..=max_y).rev() {
+            let line = (min_x..=max_x)
+                .map(|x| {
+                    let cell = Coordinate { x, y, z: 0 };
+
+                    match knots.iter().position(|knot| *knot == cell) {
+                        Some(0) => 'H',
+                        Some(index) if index == 1 && knots.len() == 2 => 'T',
+                        Some(index) => char::from_digit(index as u32 % 36, 36).unwrap_or('?'),
+                        None if cell == start => 's',
+                        None if visited.contains(&cell) => '#',
+                        None => '.',
+                    }
+                })
+                .collect::<String>();
 
-    let instructions = input
+            writeln!(f, "{}", line)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// How often the rope is drawn while simulating.
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum DrawMode {
+    /// After every single step of the head
+    Step,
+    /// After every instruction
+    Instruction,
+}
+
+/// Simulates a rope, drawing it in the puzzle's format before the first instruction
+/// and after every step or instruction.
+fn draw(input: &[String], knots_count: usize, tracked: &[usize], mode: DrawMode) -> Result<String> {
+    let mut rope = KnottedRope::new(knots_count, tracked)?;
+    let mut output = format!("== Initial State ==\n\n{}\n", rope);
+
+    for instruction in parse_instructions(input)? {
+        output.push_str(&format!("== {} ==\n\n", instruction));
+
+        match mode {
+            DrawMode::Step => {
+                for _ in 0..instruction.times {
+                    rope.step(&instruction.direction);
+                    output.push_str(&format!("{}\n", rope));
+                }
+            }
+            DrawMode::Instruction => {
+                rope.update(&instruction);
+                output.push_str(&format!("{}\n", rope));
+            }
+        }
+    }
+
+    Ok(output)
+}
+
+fn parse_instructions(input: &[String]) -> Result<Vec<Instruction>> {
+    input
         .iter()
-        .map(|line| line.parse::<Instruction>().expect("Invalid input"))
-        .collect::<Vec<_>>();
+        .enumerate()
+        .map(|(index, line)| {
+            line.parse::<Instruction>()
+                .with_context(|| format!("Line {}: `{}`", index + 1, line))
+        })
+        .collect()
+}
+
+/// Simulates a rope, returning the number of cells visited by each tracked knot.
+fn visited_by_knots(
+    input: &[String],
+    knots_count: usize,
+    tracked: &[usize],
+) -> Result<Vec<(usize, usize)>> {
+    let mut rope = KnottedRope::new(knots_count, tracked)?;
 
-    for instruction in instructions {
+    for instruction in parse_instructions(input)? {
         rope.update(&instruction);
     }
 
-    rope.tail().visited.len()
+    Ok(rope.visited_counts())
+}
+
+fn visited_by_tail(input: &[String], knots_count: usize) -> usize {
+    let tail = knots_count - 1;
+    let visited =
+        visited_by_knots(input, knots_count, &[tail]).expect("Failed to simulate the rope");
+
+    visited[0].1
 }
 
 fn part1(input: &[String]) -> usize {
-    visited_by_tail::<2>(input)
+    visited_by_tail(input, 2)
 }
 
 fn part2(input: &[String]) -> usize {
-    visited_by_tail::<10>(input)
+    visited_by_tail(input, 10)
 }
 
-pub fn solve() {
+pub fn solve(options: &Options) {
     let input: Vec<String> = read_input(Date::Day09).expect("failed to read input");
 
+    if let Some(knots_count) = options.knots {
+        let tracked = match &options.track {
+            Some(tracked) => tracked.clone(),
+            None => (0..knots_count).collect(),
+        };
+
+        for (index, visited) in
+            visited_by_knots(&input, knots_count, &tracked).expect("Failed to simulate the rope")
+        {
+            println!("Day 9 knot {}: visited {} cells", index, visited);
+        }
+    }
+
+    if let Some(mode) = options.draw {
+        let knots_count = options.knots.unwrap_or(10);
+        let tracked = match &options.track {
+            Some(tracked) => tracked.clone(),
+            None => vec![knots_count.saturating_sub(1)],
+        };
+        let drawing =
+            draw(&input, knots_count, &tracked, mode).expect("Failed to simulate the rope");
+
+        match &options.draw_file {
+            Some(path) => write(path, drawing).expect("Failed to write drawing"),
+            None => print!("{}", drawing),
+        }
+    }
+
     println!("Day 1 Part 1: {:?}", part1(&input));
     println!("Day 1 Part 2: {:?}", part2(&input));
 }
@@ -227,4 +671,145 @@ U 20";
         let actual = get_input(BIGGER_INPUT);
         assert_eq!(part2(&actual), 36);
     }
+
+    #[test]
+    fn day09_visited_by_knots() {
+        let actual = get_input(BIGGER_INPUT);
+        let visited = visited_by_knots(&actual, 10, &[9, 0, 5]).unwrap();
+        assert_eq!(visited[0].0, 0);
+        assert_eq!(visited[1].0, 5);
+        assert_eq!(visited[2], (9, 36));
+
+        let actual = get_input(BASE_INPUT);
+        assert_eq!(visited_by_knots(&actual, 2, &[1]).unwrap(), [(1, 13)]);
+        assert!(visited_by_knots(&actual, 2, &[2]).is_err());
+        assert!(visited_by_knots(&actual, 0, &[]).is_err());
+    }
+
+    #[test]
+    fn day09_draw() {
+        let actual = get_input(BASE_INPUT);
+        let drawing = draw(&actual, 2, &[1], DrawMode::Instruction).unwrap();
+
+        assert!(drawing.starts_with("== Initial State ==\n\nH\n\n== R 4 ==\n\ns##TH\n\n"));
+        assert!(drawing.ends_with(
+            "== R 2 ==
+
+..##.
+...##
+.TH##
+....#
+s###.
+
+"
+        ));
+
+        let drawing = draw(&actual, 10, &[9], DrawMode::Step).unwrap();
+        assert!(drawing.contains("== R 4 ==\n\n1H\n\n21H\n\n321H\n\n4321H\n\n"));
+    }
+
+    #[test]
+    fn day09_parse_directions() {
+        let parse = |s: &str| s.parse::<Instruction>().unwrap().to_string();
+
+        assert_eq!(parse("R 4"), "R 4");
+        assert_eq!(parse("RU 3"), "UR 3");
+        assert_eq!(parse("DLB 1"), "DLB 1");
+        assert_eq!(parse("0,1 2"), "U 2");
+        assert_eq!(parse("2,-1,0 5"), "2,-1,0 5");
+        assert!("X 1".parse::<Instruction>().is_err());
+        assert!("1,2,3,4 1".parse::<Instruction>().is_err());
+        assert!("RL 3".parse::<Instruction>().is_err());
+        assert!("0,0 2".parse::<Instruction>().is_err());
+    }
+
+    #[test]
+    fn day09_invalid_input() {
+        let input = get_input("R 4\nX 1");
+
+        let error = visited_by_knots(&input, 2, &[1]).unwrap_err();
+        assert_eq!(error.to_string(), "Line 2: `X 1`");
+        assert!(draw(&input, 2, &[1], DrawMode::Instruction).is_err());
+    }
+
+    #[test]
+    fn day09_long_steps() {
+        // Knots keep following until they touch the knot in front again.
+        for instruction in ["2,0 5", "RR 5"] {
+            let mut rope = KnottedRope::new(2, &[1]).unwrap();
+            rope.update(&instruction.parse().unwrap());
+
+            assert_eq!(rope.knots[0], Coordinate { x: 10, y: 0, z: 0 });
+            assert_eq!(rope.knots[1], Coordinate { x: 9, y: 0, z: 0 });
+            assert_eq!(rope.visited_counts(), [(1, 10)]);
+        }
+
+        let mut rope = KnottedRope::new(4, &[3]).unwrap();
+        rope.update(&"2,-1,3 4".parse().unwrap());
+
+        assert_eq!(rope.knots[0], Coordinate { x: 8, y: -4, z: 12 });
+        assert!(rope
+            .knots
+            .windows(2)
+            .all(|pair| pair[0].is_adjacent_or_overlaps(&pair[1])));
+    }
+
+    #[test]
+    fn day09_diagonal_and_3d_moves() {
+        // Moving diagonally drags the rope along the diagonal.
+        let actual = get_input("UR 4");
+        assert_eq!(visited_by_knots(&actual, 3, &[2]).unwrap(), [(2, 3)]);
+
+        let mut rope = KnottedRope::new(3, &[2]).unwrap();
+        rope.update(&"UF 3".parse().unwrap());
+        assert_eq!(rope.knots[2], Coordinate { x: 0, y: 1, z: 1 });
+
+        rope.update(&"R 2".parse().unwrap());
+        assert_eq!(rope.knots[1], Coordinate { x: 1, y: 3, z: 3 });
+        assert_eq!(rope.knots[2], Coordinate { x: 1, y: 2, z: 2 });
+    }
+
+    #[test]
+    fn day09_long_instructions() {
+        let actual = get_input("R 1000000000\nU 1000000000");
+        let visited = visited_by_knots(&actual, 10, &[0, 9]).unwrap();
+        assert_eq!(visited, [(0, 2_000_000_001), (9, 1_999_999_983)]);
+
+        // After the sideways move the tail trails the head diagonally,
+        // so the rope never lies straight along the diagonal but still slides.
+        let actual = get_input("R 2\nUR 1000000000");
+        let visited = visited_by_knots(&actual, 2, &[0, 1]).unwrap();
+        assert_eq!(visited, [(0, 1_000_000_003), (1, 1_000_000_002)]);
+    }
+
+    #[test]
+    fn day09_fast_path_matches_step_by_step() {
+        let inputs = [
+            BASE_INPUT,
+            BIGGER_INPUT,
+            "R 50\nU 30\nL 80\nD 60\nR 100\nU 100\nUL 40\nDR 90\nL 20",
+            "UR 30\nDL 15\nUL 20\nR 40\nDF 25\nUB 30\nL 10\nRF 12",
+            "R 20\nU 1\nL 20\nU 1\nR 20\nD 5\nL 3\nU 10",
+            "R 2\nUR 300\nDF 40\nR 3\nUB 50",
+        ];
+
+        for input in inputs {
+            for knots_count in [1, 2, 3, 10] {
+                let tracked = (0..knots_count).collect::<Vec<_>>();
+                let mut fast = KnottedRope::new(knots_count, &tracked).unwrap();
+                let mut slow = KnottedRope::new(knots_count, &tracked).unwrap();
+
+                for instruction in parse_instructions(&get_input(input)).unwrap() {
+                    fast.update(&instruction);
+
+                    for _ in 0..instruction.times {
+                        slow.step(&instruction.direction);
+                    }
+                }
+
+                assert_eq!(fast.knots, slow.knots);
+                assert_eq!(fast.visited_counts(), slow.visited_counts());
+            }
+        }
+    }
 }
@@ -0,0 +1,299 @@
+use std::{
+    fmt::Display,
+    ops::{Add, Sub},
+};
+
+/// An integer type that an [`IntervalSet`] can hold.
+pub trait Value: Copy + Ord + Add<Output = Self> + Sub<Output = Self> + From<u8> {
+    const MAX: Self;
+
+    /// Gets the next value, or `None` for the largest value.
+    fn successor(self) -> Option<Self>;
+}
+
+macro_rules! impl_value {
+    ($($type:ty),*) => {
+        $(impl Value for $type {
+            const MAX: Self = <$type>::MAX;
+
+            fn successor(self) -> Option<Self> {
+                self.checked_add(1)
+            }
+        })*
+    };
+}
+
+impl_value!(u8, u16, u32, u64, usize, i16, i32, i64, isize);
+
+/// A set of values stored as sorted, disjoint inclusive ranges.
+///
+/// Overlapping and adjacent ranges are merged, so a set has exactly one representation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntervalSet<T> {
+    ranges: Vec<(T, T)>,
+}
+
+impl<T> Default for IntervalSet<T> {
+    fn default() -> Self {
+        Self { ranges: Vec::new() }
+    }
+}
+
+impl<T: Value> IntervalSet<T> {
+    /// Merges inclusive ranges given in any order into a set.
+    /// Empty ranges, where the start is after the end, are ignored.
+    pub fn merge(ranges: impl IntoIterator<Item = (T, T)>) -> Self {
+        let mut ranges = ranges
+            .into_iter()
+            .filter(|(start, end)| start <= end)
+            .collect::<Vec<_>>();
+        ranges.sort_unstable();
+
+        let mut merged: Vec<(T, T)> = Vec::with_capacity(ranges.len());
+        for (start, end) in ranges {
+            match merged.last_mut() {
+                // Nothing comes after the largest value, so every range merges into one ending there.
+                Some((_, last_end)) if last_end.successor().is_none_or(|next| start <= next) => {
+                    *last_end = (*last_end).max(end)
+                }
+                _ => merged.push((start, end)),
+            }
+        }
+
+        Self { ranges: merged }
+    }
+
+    /// Gets the values covered by at least `count` of the ranges,
+    /// found with a single sweep over the sorted range endpoints.
+    /// Values are only considered within the span of the ranges,
+    /// so a `count` of 0 gives that whole span.
+    pub fn covered_at_least(ranges: impl IntoIterator<Item = (T, T)>, count: usize) -> Self {
+        if count == 0 {
+            let span = Self::merge(ranges).span();
+            return Self::merge(span);
+        }
+
+        // Coverage goes up at the start of a range and down right after its end.
+        // Ranges ending at the largest value never go down again.
+        let mut events = ranges
+            .into_iter()
+            .filter(|(start, end)| start <= end)
+            .flat_map(|(start, end)| [Some((start, 1)), end.successor().map(|after| (after, -1))])
+            .flatten()
+            .collect::<Vec<(T, isize)>>();
+        events.sort_unstable();
+
+        let mut covered = Vec::new();
+        let mut depth = 0;
+
+        for (index, &(position, change)) in events.iter().enumerate() {
+            depth += change;
+
+            match events.get(index + 1) {
+                Some(&(next, _)) if next != position && depth >= count as isize => {
+                    covered.push((position, next - T::from(1)))
+                }
+                None if depth >= count as isize => covered.push((position, T::MAX)),
+                _ => {}
+            }
+        }
+
+        Self::merge(covered)
+    }
+
+    /// Adds a range to the set.
+    pub fn insert(&mut self, start: T, end: T) {
+        *self = Self::merge(self.ranges.iter().copied().chain([(start, end)]));
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        Self::merge(self.ranges.iter().chain(&other.ranges).copied())
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        let mut ranges = Vec::new();
+        let (mut i, mut j) = (0, 0);
+
+        while let (Some(&(start, end)), Some(&(other_start, other_end))) =
+            (self.ranges.get(i), other.ranges.get(j))
+        {
+            if start.max(other_start) <= end.min(other_end) {
+                ranges.push((start.max(other_start), end.min(other_end)));
+            }
+
+            // Whichever range ends first cannot overlap anything else.
+            if end < other_end {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+
+        Self { ranges }
+    }
+
+    /// Gets the values in this set that are not in `other`.
+    pub fn difference(&self, other: &Self) -> Self {
+        let mut ranges = Vec::new();
+        let mut first = 0;
+
+        for &(start, end) in &self.ranges {
+            // Skip the ranges of `other` that end before this one starts.
+            while other
+                .ranges
+                .get(first)
+                .is_some_and(|&(_, other_end)| other_end < start)
+            {
+                first += 1;
+            }
+
+            let mut current = Some(start);
+
+            for &(other_start, other_end) in &other.ranges[first..] {
+                let Some(from) = current else { break };
+                if other_start > end {
+                    break;
+                }
+
+                if other_start > from {
+                    ranges.push((from, other_start - T::from(1)));
+                }
+
+                current = (other_end < end).then(|| from.max(other_end + T::from(1)));
+            }
+
+            if let Some(from) = current {
+                ranges.push((from, end));
+            }
+        }
+
+        Self { ranges }
+    }
+
+    pub fn contains(&self, value: T) -> bool {
+        let index = self.ranges.partition_point(|&(_, end)| end < value);
+        self.ranges
+            .get(index)
+            .is_some_and(|&(start, _)| start <= value)
+    }
+
+    /// Gets the number of values in the set.
+    /// Overflows if that number does not fit in `T`, as for a set of every value.
+    pub fn coverage(&self) -> T {
+        self.ranges.iter().fold(T::from(0), |sum, &(start, end)| {
+            sum + (end - start) + T::from(1)
+        })
+    }
+
+    /// Gets the smallest and largest value in the set.
+    pub fn span(&self) -> Option<(T, T)> {
+        Some((self.ranges.first()?.0, self.ranges.last()?.1))
+    }
+
+    pub fn ranges(&self) -> &[(T, T)] {
+        &self.ranges
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+}
+
+impl<T: Display> Display for IntervalSet<T> {
+    /// Writes the ranges like `2-4,6-8`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (index, (start, end)) in self.ranges.iter().enumerate() {
+            if index > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{}-{}", start, end)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interval_set_merge() {
+        let set = IntervalSet::merge([(6, 8), (2, 4), (5, 5), (12, 10), (20, 25), (21, 22)]);
+
+        assert_eq!(set.ranges(), [(2, 8), (20, 25)]);
+        assert_eq!(set.coverage(), 13u32);
+        assert_eq!(set.span(), Some((2, 25)));
+        assert_eq!(set.to_string(), "2-8,20-25");
+        assert!(set.contains(20));
+        assert!(!set.contains(9));
+        assert!(!set.contains(26));
+    }
+
+    #[test]
+    fn interval_set_algebra() {
+        let first = IntervalSet::merge([(1, 10), (20, 30)]);
+        let second = IntervalSet::merge([(5, 8), (10, 22), (28, 40)]);
+
+        assert_eq!(first.union(&second).ranges(), [(1, 40)]);
+        assert_eq!(
+            first.intersection(&second).ranges(),
+            [(5, 8), (10, 10), (20, 22), (28, 30)]
+        );
+        assert_eq!(
+            first.difference(&second).ranges(),
+            [(1, 4), (9, 9), (23, 27)]
+        );
+        assert_eq!(second.difference(&first).ranges(), [(11, 19), (31, 40)]);
+        assert!(first.difference(&first).is_empty());
+
+        let mut set = first.clone();
+        set.insert(11, 19);
+        assert_eq!(set.ranges(), [(1, 30)]);
+    }
+
+    #[test]
+    fn interval_set_upper_bound() {
+        let set = IntervalSet::merge([(1u32, u32::MAX), (3, 4)]);
+        assert_eq!(set.ranges(), [(1, u32::MAX)]);
+        assert_eq!(set.coverage(), u32::MAX);
+
+        let set = IntervalSet::merge([(250u8, 255), (0, 249)]);
+        assert_eq!(set.ranges(), [(0, 255)]);
+
+        let set = IntervalSet::merge([(10u8, 255)]);
+        assert_eq!(
+            set.difference(&IntervalSet::merge([(20, 255)])).ranges(),
+            [(10, 19)]
+        );
+        assert_eq!(
+            set.difference(&IntervalSet::merge([(0, 20)])).ranges(),
+            [(21, 255)]
+        );
+
+        let ranges = [(5u8, 255), (100, 255), (100, 200)];
+        assert_eq!(
+            IntervalSet::covered_at_least(ranges, 2).ranges(),
+            [(100, 255)]
+        );
+        assert_eq!(
+            IntervalSet::covered_at_least(ranges, 3).ranges(),
+            [(100, 200)]
+        );
+    }
+
+    #[test]
+    fn interval_set_covered_at_least() {
+        let ranges = [(1, 5), (3, 7), (4, 4), (6, 9)];
+
+        assert_eq!(IntervalSet::covered_at_least(ranges, 1).ranges(), [(1, 9)]);
+        assert_eq!(IntervalSet::covered_at_least(ranges, 2).ranges(), [(3, 7)]);
+        assert_eq!(IntervalSet::covered_at_least(ranges, 3).ranges(), [(4, 4)]);
+        assert!(IntervalSet::covered_at_least(ranges, 4).is_empty());
+        assert_eq!(
+            IntervalSet::covered_at_least([(1, 2), (6, 9)], 0).ranges(),
+            [(1, 9)]
+        );
+        assert!(IntervalSet::<u32>::covered_at_least([], 0).is_empty());
+    }
+}
@@ -1,4 +1,9 @@
-use std::{fs::read_to_string, str::FromStr};
+use std::{
+    fs::read_to_string,
+    io::{stdin, Read},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
 use crate::utils::date::Date;
 use anyhow::Result;
@@ -14,3 +19,17 @@ where
         .filter_map(|line| line.parse::<T>().ok())
         .collect())
 }
+
+/// Reads the input from the given file, or from stdin if the file is `-`.
+/// Falls back to the bundled input when no file is given.
+pub fn read_input_file_or(path: &Option<PathBuf>, bundled: &str) -> Result<String> {
+    match path {
+        Some(path) if path == Path::new("-") => {
+            let mut input = String::new();
+            stdin().read_to_string(&mut input)?;
+            Ok(input)
+        }
+        Some(path) => Ok(read_to_string(path)?),
+        None => Ok(bundled.to_string()),
+    }
+}
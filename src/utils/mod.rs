@@ -1,2 +1,5 @@
 pub mod date;
+pub mod interval;
 pub mod io;
+pub mod options;
+pub mod tree;
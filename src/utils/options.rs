@@ -0,0 +1,117 @@
+use std::path::PathBuf;
+
+use crate::solutions::{day02::Game, day05::CraneModel, day09::DrawMode};
+
+/// Optional command line flags that change how a given day is solved.
+#[derive(clap::Args, Clone, Debug)]
+pub struct Options {
+    /// Read the puzzle input from this file instead of the bundled one, or from stdin if `-`
+    /// (days 5 and 6)
+    #[arg(long)]
+    pub input: Option<PathBuf>,
+
+    /// Day 2: score every interpretation of the strategy guide
+    #[arg(long)]
+    pub analyse: bool,
+
+    /// Day 2: the game to play: `rps`, `rpsls`, a number of choices or the choices in cycle order
+    #[arg(long)]
+    pub game: Option<Game>,
+
+    /// Day 2: reject blank lines and extra whitespace in the strategy guide
+    #[arg(long)]
+    pub strict: bool,
+
+    /// Day 3: print every item shared by the compartments and by the groups
+    #[arg(long)]
+    pub common: bool,
+
+    /// Day 3: number of elves in a group
+    #[arg(long, default_value_t = 3)]
+    pub group_size: usize,
+
+    /// Day 3: check the rucksacks and groups and report anything wrong with them
+    #[arg(long)]
+    pub validate: bool,
+
+    /// Day 4: print the sections covered by no elf and by at least this many elves
+    #[arg(long, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    pub coverage: Option<usize>,
+
+    /// Day 4: analyse overlaps between all elves instead of only within pairs
+    #[arg(long)]
+    pub overlaps: bool,
+
+    /// Day 5: replay the crane moves in the terminal
+    #[arg(long)]
+    pub animate: bool,
+
+    /// Day 5: delay between animation frames in milliseconds
+    #[arg(long, default_value_t = 200)]
+    pub delay: u64,
+
+    /// Day 5: print the crates after every move as plain text frames
+    #[arg(long)]
+    pub frames: bool,
+
+    /// Day 5: solve with a single crane model instead of both parts
+    #[arg(long, value_enum)]
+    pub crane: Option<CraneModel>,
+
+    /// Day 5: how many crates the limited capacity crane can lift at once
+    #[arg(long, default_value_t = 3)]
+    pub capacity: usize,
+
+    /// Day 5: save the final stacks of the last crane to this file, in the input format
+    #[arg(long)]
+    pub save: Option<PathBuf>,
+
+    /// Day 6: list the end positions of all markers with this many distinct characters
+    /// in the bundled input
+    #[arg(long, conflicts_with = "input")]
+    pub window: Option<usize>,
+
+    /// Day 7: print the parsed file system as a tree and a `du`-style listing
+    #[arg(long)]
+    pub tree: bool,
+
+    /// Day 7: tolerate malformed terminal transcripts instead of failing
+    #[arg(long)]
+    pub lenient: bool,
+
+    /// Day 7: total size of the disk in bytes
+    #[arg(long, default_value_t = 70_000_000)]
+    pub disk_size: u64,
+
+    /// Day 7: unused space in bytes required for the update
+    #[arg(long, default_value_t = 30_000_000)]
+    pub required_space: u64,
+
+    /// Day 8: scan every direction from every tree instead of the linear-time sweep
+    #[arg(long)]
+    pub naive: bool,
+
+    /// Day 8: print the visibility mask and a scenic score heatmap in colour
+    #[arg(long)]
+    pub render: bool,
+
+    /// Day 8: write the visibility mask and scenic scores as PGM images to this directory
+    #[arg(long)]
+    pub pgm: Option<PathBuf>,
+
+    /// Day 9: simulate a rope with this many knots and report the cells visited per knot
+    #[arg(long)]
+    pub knots: Option<usize>,
+
+    /// Day 9: comma-separated indices of the knots to track, counting from the head at 0
+    #[arg(long, value_delimiter = ',')]
+    pub track: Option<Vec<usize>>,
+
+    /// Day 9: draw the rope and visited cells after every step or instruction
+    #[arg(long, value_enum)]
+    pub draw: Option<DrawMode>,
+
+    /// Day 9: write the drawing to this file instead of the terminal
+    #[arg(long)]
+    pub draw_file: Option<PathBuf>,
+}
@@ -0,0 +1,228 @@
+use std::{iter::successors, ops::AddAssign};
+
+/// Index of a node in a [`Tree`].
+pub type NodeId = usize;
+
+struct Node<T> {
+    value: T,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+}
+
+/// An index-arena tree where every node knows its parent and its children.
+///
+/// Node ids stay valid for as long as the node is in the tree.
+/// Removed nodes leave a hole in the arena, so ids are never reused.
+pub struct Tree<T> {
+    nodes: Vec<Option<Node<T>>>,
+}
+
+impl<T> Tree<T> {
+    /// Creates a tree with a single root node.
+    pub fn new(root: T) -> Self {
+        Self {
+            nodes: vec![Some(Node {
+                value: root,
+                parent: None,
+                children: vec![],
+            })],
+        }
+    }
+
+    /// Gets the id of the root node.
+    pub fn root(&self) -> NodeId {
+        0
+    }
+
+    /// Gets the number of nodes in the tree.
+    pub fn len(&self) -> usize {
+        self.nodes.iter().flatten().count()
+    }
+
+    /// A tree always has a root, so it is never empty.
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// Inserts a value as the last child of `parent` and returns its id.
+    pub fn insert(&mut self, parent: NodeId, value: T) -> NodeId {
+        let id = self.nodes.len();
+
+        self.node_mut(parent).children.push(id);
+        self.nodes.push(Some(Node {
+            value,
+            parent: Some(parent),
+            children: vec![],
+        }));
+
+        id
+    }
+
+    /// Removes a node and all of its descendants, returning the node's value.
+    /// Returns `None` for the root, which cannot be removed, and for ids that
+    /// are not in the tree.
+    pub fn remove(&mut self, id: NodeId) -> Option<T> {
+        let parent = self.nodes.get(id)?.as_ref()?.parent?;
+        self.node_mut(parent).children.retain(|&child| child != id);
+
+        let descendants = self.pre_order(id).skip(1).collect::<Vec<_>>();
+        for descendant in descendants {
+            self.nodes[descendant] = None;
+        }
+
+        self.nodes[id].take().map(|node| node.value)
+    }
+
+    pub fn get(&self, id: NodeId) -> Option<&T> {
+        self.nodes.get(id)?.as_ref().map(|node| &node.value)
+    }
+
+    pub fn get_mut(&mut self, id: NodeId) -> Option<&mut T> {
+        self.nodes.get_mut(id)?.as_mut().map(|node| &mut node.value)
+    }
+
+    /// Gets the parent of a node, or `None` for the root.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the node is not in the tree.
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.node(id).parent
+    }
+
+    /// Gets the children of a node in insertion order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the node is not in the tree.
+    pub fn children(&self, id: NodeId) -> &[NodeId] {
+        &self.node(id).children
+    }
+
+    /// Iterates over all nodes in the tree in id order.
+    pub fn iter(&self) -> impl Iterator<Item = (NodeId, &T)> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter_map(|(id, node)| Some((id, &node.as_ref()?.value)))
+    }
+
+    /// Iterates over the parent, grandparent etc. of a node up to the root.
+    pub fn ancestors(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        successors(self.parent(id), |&ancestor| self.parent(ancestor))
+    }
+
+    /// Iterates over a subtree, visiting each node before its children.
+    pub fn pre_order(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        let mut stack = vec![id];
+
+        std::iter::from_fn(move || {
+            let id = stack.pop()?;
+            stack.extend(self.children(id).iter().rev());
+            Some(id)
+        })
+    }
+
+    /// Iterates over a subtree, visiting each node after its children.
+    pub fn post_order(&self, id: NodeId) -> impl Iterator<Item = NodeId> {
+        let mut order = Vec::new();
+        let mut stack = vec![id];
+
+        // Visit each node before its children, right to left, then reverse.
+        while let Some(id) = stack.pop() {
+            order.push(id);
+            stack.extend(self.children(id));
+        }
+
+        order.into_iter().rev()
+    }
+
+    /// Folds over every node in a subtree in pre-order.
+    pub fn fold_subtree<A, F>(&self, id: NodeId, init: A, mut f: F) -> A
+    where
+        F: FnMut(A, &T) -> A,
+    {
+        self.pre_order(id)
+            .fold(init, |acc, id| f(acc, &self.node(id).value))
+    }
+
+    /// Computes `value` summed over the subtree of every node at once.
+    /// The result is indexed by [`NodeId`]; removed nodes get `A::default()`.
+    pub fn subtree_sums<A, F>(&self, value: F) -> Vec<A>
+    where
+        A: Copy + Default + AddAssign,
+        F: Fn(&T) -> A,
+    {
+        let mut sums = vec![A::default(); self.nodes.len()];
+
+        for id in self.post_order(self.root()) {
+            sums[id] += value(&self.node(id).value);
+
+            if let Some(parent) = self.parent(id) {
+                let sum = sums[id];
+                sums[parent] += sum;
+            }
+        }
+
+        sums
+    }
+
+    fn node(&self, id: NodeId) -> &Node<T> {
+        self.nodes[id].as_ref().expect("Node has been removed")
+    }
+
+    fn node_mut(&mut self, id: NodeId) -> &mut Node<T> {
+        self.nodes[id].as_mut().expect("Node has been removed")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds the tree `1 -> (2 -> (4, 5), 3)`.
+    fn get_tree() -> Tree<u32> {
+        let mut tree = Tree::new(1);
+        let two = tree.insert(tree.root(), 2);
+        tree.insert(tree.root(), 3);
+        tree.insert(two, 4);
+        tree.insert(two, 5);
+        tree
+    }
+
+    fn values(tree: &Tree<u32>, ids: impl Iterator<Item = NodeId>) -> Vec<u32> {
+        ids.map(|id| *tree.get(id).unwrap()).collect()
+    }
+
+    #[test]
+    fn tree_traversal() {
+        let tree = get_tree();
+
+        assert_eq!(values(&tree, tree.pre_order(tree.root())), [1, 2, 4, 5, 3]);
+        assert_eq!(values(&tree, tree.post_order(tree.root())), [4, 5, 2, 3, 1]);
+        assert_eq!(values(&tree, tree.ancestors(4)), [2, 1]);
+    }
+
+    #[test]
+    fn tree_aggregation() {
+        let tree = get_tree();
+
+        assert_eq!(tree.subtree_sums(|&value| value), [15, 11, 3, 4, 5]);
+        assert_eq!(tree.fold_subtree(1, 1, |acc, value| acc * value), 40);
+    }
+
+    #[test]
+    fn tree_remove() {
+        let mut tree = get_tree();
+
+        assert_eq!(tree.remove(tree.root()), None);
+        assert_eq!(tree.remove(1), Some(2));
+        assert_eq!(tree.remove(1), None);
+        assert_eq!(tree.remove(3), None);
+        assert_eq!(tree.remove(10), None);
+        assert_eq!(tree.len(), 2);
+        assert_eq!(tree.get(4), None);
+        assert_eq!(tree.children(tree.root()), [2]);
+        assert_eq!(tree.subtree_sums(|&value| value), [4, 0, 3, 0, 0]);
+    }
+}